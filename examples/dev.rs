@@ -14,22 +14,21 @@ fn main() {
     }
 }
 
-fn run() -> Result<(), Box<Error>> {
-    let mut params: Parameters = Default::default();
-
+fn run() -> Result<(), Box<dyn Error>> {
     let mut connection = Connection::new();
     connection.open(
         |timestamp, midi_channel, control_change, value| {
-            println!("{}: {:02X?} {:02X?} {:02X?}", timestamp, midi_channel, control_change, value);
+            println!("{}: {:02X?} {:02X?} {:02X?}", timestamp, midi_channel.get(), control_change.get(),
+                value.get());
         },
-        move |timestamp, midi_channel, command_value, function_id, data| {
+        |timestamp, global_channel, command_value, function_id, data| {
             match command_value {
                 0x7F => {
-                    params = Parameters::parse_scene_dump(&data).unwrap();
+                    let params = Parameters::parse_scene_dump(data).unwrap();
                     println!("{:#?}", params);
                 },
-                _ => println!("{}: {:02X?} {:02X?} {:02X?} {:02X?}", timestamp, midi_channel,
-                    command_value, function_id, data),
+                _ => println!("{}: {:02X?} {:02X?} {:02X?} {:02X?}", timestamp,
+                    global_channel.get(), command_value, function_id, data),
             }
         }
     )?;
@@ -41,7 +40,7 @@ fn run() -> Result<(), Box<Error>> {
         if input.trim() == "q" {
             break;
         } else if input.trim() == "w" {
-            connection.current_scene_data_dump_request(0)?;
+            connection.current_scene_data_dump_request()?;
         } else {
             sleep(Duration::from_millis(200));
         }
@@ -0,0 +1,339 @@
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_core::Stream;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+
+use super::connection::{Connection, MessageHandler, OutputSender};
+use super::control::ControlId;
+use super::device::{DeviceInfo, DeviceMode, DeviceResponse};
+use super::error::Error;
+use super::event::ControlEvent;
+use super::lookup::ControlLookup;
+use super::parameters::Parameters;
+use super::request::{self, PendingRequests, Request};
+use super::shift::Layer;
+use super::types::{CcNumber, Channel, GlobalChannel, U7};
+use super::Result;
+
+#[derive(Debug, Clone)]
+enum Reply {
+    Response(DeviceResponse),
+    SceneDump(Box<Parameters>),
+    /// The global channel of any reply from the device, used to discover it.
+    GlobalChannel(GlobalChannel),
+}
+
+struct Scene {
+    parameters: Parameters,
+    lookup: ControlLookup,
+}
+
+/// The state shared with the input handler running on midir's thread.
+struct Shared {
+    scene: Mutex<Scene>,
+    pending: Mutex<PendingRequests<Reply, oneshot::Sender<Reply>>>,
+    subscribers: Mutex<Vec<UnboundedSender<ControlEvent>>>,
+    mode: Mutex<Option<DeviceMode>>,
+}
+
+/// An async front end to the device for use with tokio, built on the `Connection` request
+/// functions. Requests time out after `get_response_timeout`, and cancelling one by dropping its
+/// future leaves no pending state behind.
+pub struct AsyncDevice {
+    connection: Mutex<Connection>,
+    shared: Arc<Shared>,
+    response_timeout: Duration,
+}
+
+impl Default for AsyncDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsyncDevice {
+    pub fn new() -> Self {
+        let parameters = Parameters::factory_default();
+        AsyncDevice {
+            connection: Mutex::new(Connection::new()),
+            shared: Arc::new(Shared {
+                scene: Mutex::new(Scene {
+                    lookup: parameters.build_lookup(),
+                    parameters,
+                }),
+                pending: Mutex::new(PendingRequests::default()),
+                subscribers: Mutex::new(Vec::new()),
+                mode: Mutex::new(None),
+            }),
+            response_timeout: Duration::from_secs(2),
+        }
+    }
+
+    /// Opens the connection, discovers the device's global channel and requests its mode and
+    /// current scene like `KorgNanokontrol2::connect`. The connection is closed again if any of
+    /// this fails.
+    pub async fn connect(&self) -> Result<()> {
+        let handler = AsyncHandler { shared: self.shared.clone() };
+        self.lock_connection().open_with_handler(handler)?;
+
+        let result = match self.discover_global_channel().await {
+            Ok(_) => {
+                let mut connection = self.lock_connection();
+                connection.mode_request().and_then(|_| connection.current_scene_data_dump_request())
+            },
+            Err(error) => Err(error),
+        };
+        if result.is_err() {
+            self.disconnect();
+        }
+        result
+    }
+
+    /// Finds the global channel the device answers on and uses it for all further requests. The
+    /// identity request is tried first, then scene dump requests on every channel.
+    pub async fn discover_global_channel(&self) -> Result<GlobalChannel> {
+        let accepts = |reply: &Reply| matches!(reply, Reply::GlobalChannel(_));
+        let reply = match self.request(accepts, |connection| connection.device_inquiry_request())
+            .await {
+            Err(Error::ResponseTimeout) =>
+                self.request(accepts, |connection| connection.probe_global_channels()).await?,
+            reply => reply?,
+        };
+        let global_channel = match reply {
+            Reply::GlobalChannel(global_channel) => global_channel,
+            _ => unreachable!("only global channels are accepted"),
+        };
+        self.set_global_channel(global_channel);
+        Ok(global_channel)
+    }
+
+    pub fn disconnect(&self) {
+        self.lock_connection().close();
+        *lock(&self.shared.mode) = None;
+    }
+
+    /// The mode last reported by the device, `None` until it answered a mode request.
+    pub fn get_mode(&self) -> Option<DeviceMode> {
+        *lock(&self.shared.mode)
+    }
+
+    pub fn get_global_channel(&self) -> GlobalChannel {
+        self.lock_connection().get_global_channel()
+    }
+
+    pub fn set_global_channel(&self, global_channel: GlobalChannel) {
+        self.lock_connection().set_global_channel(global_channel);
+    }
+
+    /// A cloneable sender for the device's output, usable without awaiting the device.
+    pub fn get_output_sender(&self) -> OutputSender {
+        self.lock_connection().get_output_sender()
+    }
+
+    pub fn get_response_timeout(&self) -> Duration {
+        self.response_timeout
+    }
+
+    pub fn set_response_timeout(&mut self, timeout: Duration) {
+        self.response_timeout = timeout;
+    }
+
+    /// The scene last fetched or written.
+    pub fn get_parameters(&self) -> Parameters {
+        lock(&self.shared.scene).parameters.clone()
+    }
+
+    /// A stream of the control changes and notes received from now on, decoded with the current
+    /// scene. Every call returns an independent stream.
+    pub fn events(&self) -> ControlEventStream {
+        let (sender, receiver) = unbounded_channel();
+        lock(&self.shared.subscribers).push(sender);
+        ControlEventStream { receiver }
+    }
+
+    /// Requests the device's current scene.
+    pub async fn fetch_scene(&self) -> Result<Parameters> {
+        let reply = self.request(
+            |reply| matches!(reply, Reply::SceneDump(_)),
+            |connection| connection.current_scene_data_dump_request()).await?;
+        match reply {
+            Reply::SceneDump(parameters) => Ok(*parameters),
+            _ => unreachable!("only scene dumps are accepted"),
+        }
+    }
+
+    /// Sends a scene to the device and writes it to the device's memory, waiting for the device to
+    /// confirm both steps.
+    pub async fn write_scene(&self, parameters: Parameters) -> Result<()> {
+        request::check_mode(self.get_mode(), DeviceMode::Normal)?;
+
+        let scene_dump = parameters.create_scene_dump();
+        self.send_request(Request::DataLoad(&scene_dump)).await?;
+        let global_channel = parameters.global_channel;
+        set_scene(&self.shared, parameters);
+        self.send_request(Request::Write).await?;
+        self.set_global_channel(global_channel);
+        Ok(())
+    }
+
+    /// Asks the device whether it is in normal or native mode.
+    pub async fn query_mode(&self) -> Result<DeviceMode> {
+        match self.send_request(Request::Mode).await? {
+            DeviceResponse::ModeData(mode) => Ok(mode),
+            _ => unreachable!("only mode data is accepted"),
+        }
+    }
+
+    /// Sends a request and waits for the response answering it.
+    async fn send_request(&self, request: Request<'_>) -> Result<DeviceResponse> {
+        let accepts = request.get_acceptor();
+        let reply = self.request(
+            move |reply| matches!(reply, Reply::Response(response) if accepts(response)),
+            |connection| request.send(connection)).await?;
+        match reply {
+            Reply::Response(response) => request.check(response),
+            _ => unreachable!("only responses are accepted"),
+        }
+    }
+
+    async fn request<A, F>(&self, accepts: A, send: F) -> Result<Reply> where
+        A: Fn(&Reply) -> bool + Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<()> {
+
+        let (sender, receiver) = oneshot::channel();
+        let id = lock(&self.shared.pending).register(accepts, sender);
+        let _pending = PendingGuard { shared: &self.shared, id };
+
+        send(&mut self.lock_connection())?;
+        match timeout(self.response_timeout, receiver).await {
+            Ok(Ok(reply)) => Ok(reply),
+            _ => Err(Error::ResponseTimeout),
+        }
+    }
+
+    fn lock_connection(&self) -> MutexGuard<'_, Connection> {
+        lock(&self.connection)
+    }
+}
+
+/// Removes a request from the pending list when it completes, times out or is cancelled.
+struct PendingGuard<'a> {
+    shared: &'a Shared,
+    id: u64,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        lock(&self.shared.pending).remove(self.id);
+    }
+}
+
+/// Control events received by an `AsyncDevice`.
+pub struct ControlEventStream {
+    receiver: UnboundedReceiver<ControlEvent>,
+}
+
+impl Stream for ControlEventStream {
+    type Item = ControlEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<ControlEvent>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+struct AsyncHandler {
+    shared: Arc<Shared>,
+}
+
+impl AsyncHandler {
+    fn publish(&self, timestamp: u64, control: Option<ControlId>, value: U7) {
+        let control = match control {
+            Some(control) => control,
+            None => return,
+        };
+        let event = ControlEvent {
+            timestamp,
+            control,
+            value,
+            virtual_channel: control.get_group_index(),
+            layer: Layer::Base,
+            motion: None,
+        };
+        lock(&self.shared.subscribers).retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    fn publish_note(&self, timestamp: u64, midi_channel: Channel, note_number: U7, on: bool) {
+        let (control, value) = {
+            let scene = lock(&self.shared.scene);
+            let control = scene.lookup.find_note(midi_channel, note_number);
+            let value = control
+                .and_then(|control| scene.parameters.get_button_value(control, on))
+                .unwrap_or(U7::MIN);
+            (control, value)
+        };
+        self.publish(timestamp, control, value);
+    }
+
+    fn deliver(&self, reply: Reply) {
+        if let Some(sender) = lock(&self.shared.pending).take_sender(&reply) {
+            sender.send(reply).ok();
+        }
+    }
+}
+
+impl MessageHandler for AsyncHandler {
+    fn control_change(&mut self, timestamp: u64, midi_channel: Channel, control_change: CcNumber,
+        value: U7) {
+        let control = lock(&self.shared.scene).lookup
+            .find_control_change(midi_channel, control_change);
+        self.publish(timestamp, control, value);
+    }
+
+    fn note_on(&mut self, timestamp: u64, midi_channel: Channel, note_number: U7, _velocity: U7) {
+        self.publish_note(timestamp, midi_channel, note_number, true);
+    }
+
+    fn note_off(&mut self, timestamp: u64, midi_channel: Channel, note_number: U7, _velocity: U7) {
+        self.publish_note(timestamp, midi_channel, note_number, false);
+    }
+
+    fn system_exclusive(&mut self, _timestamp: u64, global_channel: GlobalChannel,
+        command_value: u8, _function_id: u8, data: &[u8]) {
+        self.deliver(Reply::GlobalChannel(global_channel));
+        if command_value != 0x7F {
+            return;
+        }
+        if let Ok(parameters) = Parameters::parse_scene_dump(data) {
+            set_scene(&self.shared, parameters.clone());
+            self.deliver(Reply::SceneDump(Box::new(parameters)));
+        }
+    }
+
+    fn device_response(&mut self, _timestamp: u64, _global_channel: GlobalChannel,
+        response: DeviceResponse) {
+        if let Some(mode) = response.get_mode() {
+            *lock(&self.shared.mode) = Some(mode);
+        }
+        self.deliver(Reply::Response(response));
+    }
+
+    fn device_info(&mut self, _timestamp: u64, device_info: DeviceInfo) {
+        self.deliver(Reply::GlobalChannel(device_info.global_channel));
+    }
+}
+
+fn set_scene(shared: &Shared, parameters: Parameters) {
+    *lock(&shared.scene) = Scene {
+        lookup: parameters.build_lookup(),
+        parameters,
+    };
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
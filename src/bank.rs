@@ -0,0 +1,126 @@
+use super::data::GroupData;
+
+/// Stores the group values of every bank of virtual channels, eight channels per bank.
+#[derive(Debug)]
+pub struct Banks {
+    current: usize,
+    groups: Vec<[GroupData; 8]>,
+}
+
+impl Default for Banks {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl Banks {
+    pub fn new(count: usize) -> Self {
+        Banks {
+            current: 0,
+            groups: vec![Default::default(); count.max(1)],
+        }
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn get_current(&self) -> usize {
+        self.current
+    }
+
+    pub fn get_virtual_channel(&self, group_index: usize) -> usize {
+        self.current * 8 + group_index
+    }
+
+    /// Returns the stored values of a bank. The current bank's values live in `Data` and are only
+    /// stored here when switching away from it.
+    pub fn get_groups(&self, bank: usize) -> Option<&[GroupData; 8]> {
+        self.groups.get(bank)
+    }
+
+    /// Stores `groups` as the values of the current bank and replaces them with those of `bank`.
+    pub fn switch(&mut self, bank: usize, groups: &mut [GroupData; 8]) -> bool {
+        if bank >= self.groups.len() || bank == self.current {
+            return false;
+        }
+
+        self.groups[self.current] = *groups;
+        *groups = self.groups[bank];
+        self.current = bank;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::control::ControlId;
+    use crate::enums::TransportButton;
+    use crate::event::{BankEvent, Event};
+    use crate::types::U7;
+    use crate::KorgNanokontrol2;
+
+    const SLIDER_1: u8 = 0x00;
+    const TRACK_REWIND: u8 = 0x3A;
+    const TRACK_FASTFORWARD: u8 = 0x3B;
+
+    fn device() -> KorgNanokontrol2 {
+        let mut device = KorgNanokontrol2::new();
+        device.set_bank_count(3);
+        device
+    }
+
+    fn press(device: &KorgNanokontrol2, timestamp: u64, control_change: u8) {
+        device.receive(timestamp, &[0xB0, control_change, 0x7F]);
+        device.receive(timestamp + 1, &[0xB0, control_change, 0x00]);
+    }
+
+    #[test]
+    fn track_buttons_page_banks_and_are_swallowed() {
+        let mut device = device();
+        press(&device, 0, TRACK_FASTFORWARD);
+        press(&device, 10, TRACK_FASTFORWARD);
+        press(&device, 20, TRACK_FASTFORWARD);
+        assert_eq!(device.poll_events(), vec![
+            Event::BankChanged(BankEvent { timestamp: 0, bank: 1 }),
+            Event::BankChanged(BankEvent { timestamp: 10, bank: 2 }),
+        ]);
+        assert_eq!(device.get_bank(), 2);
+
+        press(&device, 30, TRACK_REWIND);
+        assert_eq!(device.poll_events(),
+            vec![Event::BankChanged(BankEvent { timestamp: 30, bank: 1 })]);
+        let track_rewind = ControlId::Transport(TransportButton::TrackRewind);
+        assert_eq!(device.get_data().get_value(track_rewind), U7::MIN);
+    }
+
+    #[test]
+    fn values_are_restored_per_bank() {
+        let mut device = device();
+        device.receive(0, &[0xB0, SLIDER_1, 100]);
+        press(&device, 10, TRACK_FASTFORWARD);
+        device.poll_events();
+        assert_eq!(device.get_data().get_value(ControlId::Slider(0)), U7::MIN);
+
+        device.receive(20, &[0xB0, SLIDER_1, 30]);
+        press(&device, 30, TRACK_REWIND);
+        device.poll_events();
+        assert_eq!(device.get_data().get_value(ControlId::Slider(0)), U7::from_const(100));
+        assert_eq!(device.get_banks().get_groups(1).unwrap()[0].slider_value,
+            U7::from_const(30));
+    }
+
+    #[test]
+    fn virtual_channels_are_offset_by_the_bank() {
+        let mut device = device();
+        press(&device, 0, TRACK_FASTFORWARD);
+        device.receive(10, &[0xB0, SLIDER_1 + 2, 64]);
+        let virtual_channels: Vec<_> = device.poll_events().into_iter()
+            .filter_map(|event| match event {
+                Event::Control(event) => Some(event.virtual_channel),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(virtual_channels, vec![Some(10)]);
+    }
+}
@@ -0,0 +1,353 @@
+use super::enums::*;
+use super::error::Error;
+use super::parameters::*;
+use super::types::{CcNumber, GlobalChannel, U7};
+use super::Result;
+
+/// Builds a `Parameters` scene, starting from the factory default scene. Groups and buttons are
+/// configured through closures, e.g. `.group(3, |g| g.slider_cc(13).knob_cc(23))`.
+#[derive(Debug, Clone)]
+pub struct SceneBuilder {
+    parameters: Parameters,
+    error: Option<Error>,
+}
+
+impl Default for SceneBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SceneBuilder {
+    pub fn new() -> Self {
+        Self::from_parameters(Parameters::factory_default())
+    }
+
+    pub fn from_parameters(parameters: Parameters) -> Self {
+        SceneBuilder {
+            parameters,
+            error: None,
+        }
+    }
+
+    pub fn global_channel(mut self, global_channel: u8) -> Self {
+        let result = GlobalChannel::new(global_channel)
+            .map(|global_channel| self.parameters.global_channel = global_channel);
+        keep_first_error(&mut self.error, result);
+        self
+    }
+
+    pub fn control_mode(mut self, control_mode: ControlMode) -> Self {
+        self.parameters.control_mode = control_mode;
+        self
+    }
+
+    pub fn led_mode(mut self, led_mode: LedMode) -> Self {
+        self.parameters.led_mode = led_mode;
+        self
+    }
+
+    pub fn transport_channel(mut self, channel: MidiChannel) -> Self {
+        self.parameters.transport_button_channel = channel;
+        self
+    }
+
+    pub fn custom_daw_assign(mut self, custom_daw_assign: [u8; 5]) -> Self {
+        for (i, &value) in custom_daw_assign.iter().enumerate() {
+            let result = U7::new(value)
+                .map(|value| self.parameters.custom_daw_assign[i] = value);
+            keep_first_error(&mut self.error, result);
+        }
+        self
+    }
+
+    pub fn group<F>(mut self, group_index: usize, f: F) -> Self where
+        F: FnOnce(GroupBuilder) -> GroupBuilder {
+
+        let result = match self.parameters.groups.get_mut(group_index) {
+            Some(group) => f(GroupBuilder::new(group.clone())).apply(group),
+            None => Err(Error::InvalidGroupIndex(group_index)),
+        };
+        keep_first_error(&mut self.error, result);
+        self
+    }
+
+    /// Applies `f` to every group, passing the group index along.
+    pub fn groups<F>(mut self, mut f: F) -> Self where
+        F: FnMut(usize, GroupBuilder) -> GroupBuilder {
+
+        for (i, group) in self.parameters.groups.iter_mut().enumerate() {
+            let result = f(i, GroupBuilder::new(group.clone())).apply(group);
+            keep_first_error(&mut self.error, result);
+        }
+        self
+    }
+
+    pub fn transport<F>(mut self, button_type: TransportButton, f: F) -> Self where
+        F: FnOnce(ButtonBuilder) -> ButtonBuilder {
+
+        let button = self.parameters.get_transport_button_parameters_mut(button_type);
+        let result = f(ButtonBuilder::new(button.clone())).apply(button);
+        keep_first_error(&mut self.error, result);
+        self
+    }
+
+    /// Assigns consecutive CC numbers from `first_control_change` to every control, see
+    /// `Parameters::assign_sequential_cc`.
+    pub fn sequential_cc(mut self, first_control_change: u8) -> Self {
+        let result = self.parameters.assign_sequential_cc(first_control_change);
+        keep_first_error(&mut self.error, result);
+        self
+    }
+
+    /// Validates the scene, returning the first error encountered while building it, if any, or
+    /// the first message assigned to several controls.
+    pub fn build(self) -> Result<Parameters> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        match self.parameters.build_lookup().get_ambiguities().first() {
+            Some(ambiguity) => Err(Error::AmbiguousAssignment(ambiguity.clone())),
+            None => Ok(self.parameters),
+        }
+    }
+
+}
+
+/// Records the error of `result` unless an earlier one was recorded already.
+fn keep_first_error(error: &mut Option<Error>, result: Result<()>) {
+    if let (None, Err(new_error)) = (&error, result) {
+        *error = Some(new_error);
+    }
+}
+
+/// Configures one group of a `SceneBuilder`.
+#[derive(Debug, Clone)]
+pub struct GroupBuilder {
+    parameters: ControllerGroupParameters,
+    error: Option<Error>,
+}
+
+impl GroupBuilder {
+    fn new(parameters: ControllerGroupParameters) -> Self {
+        GroupBuilder {
+            parameters,
+            error: None,
+        }
+    }
+
+    fn apply(self, group: &mut ControllerGroupParameters) -> Result<()> {
+        match self.error {
+            Some(error) => Err(error),
+            None => {
+                *group = self.parameters;
+                Ok(())
+            },
+        }
+    }
+
+    pub fn channel(mut self, channel: MidiChannel) -> Self {
+        self.parameters.channel = channel;
+        self
+    }
+
+    pub fn slider_cc(mut self, control_change: u8) -> Self {
+        let result = CcNumber::new(control_change)
+            .map(|control_change| assign_slider_cc(&mut self.parameters.slider, control_change));
+        keep_first_error(&mut self.error, result);
+        self
+    }
+
+    pub fn slider_range(mut self, min_value: u8, max_value: u8) -> Self {
+        let result = set_range(&mut self.parameters.slider, min_value, max_value);
+        keep_first_error(&mut self.error, result);
+        self
+    }
+
+    pub fn slider_disabled(mut self) -> Self {
+        self.parameters.slider.assign_type = SliderAssignType::Disable;
+        self
+    }
+
+    pub fn knob_cc(mut self, control_change: u8) -> Self {
+        let result = CcNumber::new(control_change)
+            .map(|control_change| assign_slider_cc(&mut self.parameters.knob, control_change));
+        keep_first_error(&mut self.error, result);
+        self
+    }
+
+    pub fn knob_range(mut self, min_value: u8, max_value: u8) -> Self {
+        let result = set_range(&mut self.parameters.knob, min_value, max_value);
+        keep_first_error(&mut self.error, result);
+        self
+    }
+
+    pub fn knob_disabled(mut self) -> Self {
+        self.parameters.knob.assign_type = SliderAssignType::Disable;
+        self
+    }
+
+    pub fn solo<F>(mut self, f: F) -> Self where F: FnOnce(ButtonBuilder) -> ButtonBuilder {
+        let button = &mut self.parameters.solo_button;
+        let result = f(ButtonBuilder::new(button.clone())).apply(button);
+        keep_first_error(&mut self.error, result);
+        self
+    }
+
+    pub fn solo_cc(self, control_change: u8) -> Self {
+        self.solo(|b| b.cc(control_change).momentary())
+    }
+
+    pub fn solo_toggle_cc(self, control_change: u8) -> Self {
+        self.solo(|b| b.cc(control_change).toggle())
+    }
+
+    pub fn solo_note(self, note_number: u8) -> Self {
+        self.solo(|b| b.note(note_number))
+    }
+
+    pub fn mute<F>(mut self, f: F) -> Self where F: FnOnce(ButtonBuilder) -> ButtonBuilder {
+        let button = &mut self.parameters.mute_button;
+        let result = f(ButtonBuilder::new(button.clone())).apply(button);
+        keep_first_error(&mut self.error, result);
+        self
+    }
+
+    pub fn mute_cc(self, control_change: u8) -> Self {
+        self.mute(|b| b.cc(control_change).momentary())
+    }
+
+    pub fn mute_toggle_cc(self, control_change: u8) -> Self {
+        self.mute(|b| b.cc(control_change).toggle())
+    }
+
+    pub fn mute_note(self, note_number: u8) -> Self {
+        self.mute(|b| b.note(note_number))
+    }
+
+    pub fn record<F>(mut self, f: F) -> Self where F: FnOnce(ButtonBuilder) -> ButtonBuilder {
+        let button = &mut self.parameters.record_button;
+        let result = f(ButtonBuilder::new(button.clone())).apply(button);
+        keep_first_error(&mut self.error, result);
+        self
+    }
+
+    pub fn record_cc(self, control_change: u8) -> Self {
+        self.record(|b| b.cc(control_change).momentary())
+    }
+
+    pub fn record_toggle_cc(self, control_change: u8) -> Self {
+        self.record(|b| b.cc(control_change).toggle())
+    }
+
+    pub fn record_note(self, note_number: u8) -> Self {
+        self.record(|b| b.note(note_number))
+    }
+}
+
+fn set_range(slider: &mut SliderParameters, min_value: u8, max_value: u8) -> Result<()> {
+    slider.min_value = U7::new(min_value)?;
+    slider.max_value = U7::new(max_value)?;
+    Ok(())
+}
+
+/// Configures one button of a `SceneBuilder`.
+#[derive(Debug, Clone)]
+pub struct ButtonBuilder {
+    parameters: ButtonParameters,
+    error: Option<Error>,
+}
+
+impl ButtonBuilder {
+    fn new(parameters: ButtonParameters) -> Self {
+        ButtonBuilder {
+            parameters,
+            error: None,
+        }
+    }
+
+    fn apply(self, button: &mut ButtonParameters) -> Result<()> {
+        match self.error {
+            Some(error) => Err(error),
+            None => {
+                *button = self.parameters;
+                Ok(())
+            },
+        }
+    }
+
+    pub fn cc(mut self, control_change: u8) -> Self {
+        let result = CcNumber::new(control_change)
+            .map(|control_change| assign_button_cc(&mut self.parameters, control_change));
+        keep_first_error(&mut self.error, result);
+        self
+    }
+
+    pub fn note(mut self, note_number: u8) -> Self {
+        let result = U7::new(note_number).map(|note_number| {
+            self.parameters.assign_type = ButtonAssignType::Note;
+            self.parameters.note_number = note_number;
+        });
+        keep_first_error(&mut self.error, result);
+        self
+    }
+
+    pub fn unassigned(mut self) -> Self {
+        self.parameters.assign_type = ButtonAssignType::NoAssign;
+        self
+    }
+
+    pub fn momentary(mut self) -> Self {
+        self.parameters.behavior = ButtonBehavior::Momentary;
+        self
+    }
+
+    pub fn toggle(mut self) -> Self {
+        self.parameters.behavior = ButtonBehavior::Toggle;
+        self
+    }
+
+    pub fn values(mut self, off_value: u8, on_value: u8) -> Self {
+        let result = U7::new(off_value).and_then(|off_value| {
+            self.parameters.off_value = off_value;
+            self.parameters.on_value = U7::new(on_value)?;
+            Ok(())
+        });
+        keep_first_error(&mut self.error, result);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::ControlId;
+
+    #[test]
+    fn build_rejects_ambiguous_assignments() {
+        let result = SceneBuilder::new().group(0, |g| g.slider_cc(16)).build();
+        match result {
+            Err(Error::AmbiguousAssignment(ambiguity)) =>
+                assert_eq!(ambiguity.controls, vec![ControlId::Slider(0), ControlId::Knob(0)]),
+            other => panic!("expected an ambiguous assignment, got {:?}", other),
+        }
+        assert!(SceneBuilder::new().group(0, |g| g.slider_cc(16).knob_cc(100)).build().is_ok());
+    }
+
+    #[test]
+    fn build_reports_value_errors_first() {
+        let result = SceneBuilder::new().group(0, |g| g.slider_cc(128).knob_cc(0)).build();
+        assert!(matches!(result, Err(Error::InvalidMidiValue(128))));
+    }
+
+    #[test]
+    fn sequential_cc() {
+        assert_eq!(SceneBuilder::new().sequential_cc(0).build().unwrap(),
+            Parameters::sequential_cc());
+
+        let parameters = SceneBuilder::new().sequential_cc(77).build().unwrap();
+        assert_eq!(parameters.groups[0].slider.note_number.get(), 77);
+        assert_eq!(parameters.record.note_number.get(), 127);
+        assert!(SceneBuilder::new().sequential_cc(78).build().is_err());
+    }
+}
@@ -1,6 +1,16 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
 use midir::{MidiInput, MidiOutput, MidiInputConnection, MidiOutputConnection};
 
+use super::control::ControlId;
+use super::device::{DeviceInfo, DeviceResponse};
+use super::enums::ButtonAssignType;
 use super::error::Error;
+use super::parameters::Parameters;
+use super::types::{CcNumber, Channel, GlobalChannel, U7};
 use super::Result;
 
 // #[derive(Debug, Copy, Clone)]
@@ -20,7 +30,7 @@ pub enum Command {
 }
 
 impl Command {
-    fn try_parse(n: u8) -> Option<Self> {
+    pub fn try_parse(n: u8) -> Option<Self> {
         match n {
             0x00 => Some(Command::NativeModeInOutRequest),
             0x1F => Some(Command::DataDumpRequest),
@@ -51,7 +61,7 @@ pub enum Function {
 }
 
 impl Function {
-    fn try_parse(n: u8) -> Option<Self> {
+    pub fn try_parse(n: u8) -> Option<Self> {
         match n {
             0x10 => Some(Function::CurrentSceneDataDump),
             0x23 => Some(Function::DataLoadCompleted),
@@ -72,7 +82,7 @@ pub enum RequestType {
     ModeRequest                 = 0x12,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum IoType {
     Out = 0x00,
     In  = 0x01,
@@ -84,26 +94,124 @@ pub enum DataFormat {
     Variable,
 }
 
+/// Receives the messages decoded by `decode_message`. Every method does nothing by default, so
+/// implementors only pick the messages they care about.
+pub trait MessageHandler {
+    fn control_change(&mut self, _timestamp: u64, _midi_channel: Channel,
+        _control_change: CcNumber, _value: U7) {}
+
+    fn note_on(&mut self, _timestamp: u64, _midi_channel: Channel, _note_number: U7,
+        _velocity: U7) {}
+
+    /// Also called for Note On messages with a velocity of 0.
+    fn note_off(&mut self, _timestamp: u64, _midi_channel: Channel, _note_number: U7,
+        _velocity: U7) {}
+
+    /// Any other channel voice message, such as pitch bend or aftertouch, undecoded.
+    fn channel_message(&mut self, _timestamp: u64, _message: &[u8]) {}
+
+    fn system_exclusive(&mut self, _timestamp: u64, _global_channel: GlobalChannel,
+        _command_value: u8, _function_id: u8, _data: &[u8]) {}
+
+    /// A packet communication or native mode reply, also passed to `system_exclusive`.
+    fn device_response(&mut self, _timestamp: u64, _global_channel: GlobalChannel,
+        _response: DeviceResponse) {}
+
+    /// A reply to `Connection::device_inquiry_request`.
+    fn device_info(&mut self, _timestamp: u64, _device_info: DeviceInfo) {}
+}
+
+struct CallbackHandler<F, G> {
+    control_change_callback: F,
+    system_exclusive_callback: G,
+}
+
+impl<F, G> MessageHandler for CallbackHandler<F, G> where
+    F: FnMut(u64, Channel, CcNumber, U7),
+    G: FnMut(u64, GlobalChannel, u8, u8, &[u8]) {
+
+    fn control_change(&mut self, timestamp: u64, midi_channel: Channel, control_change: CcNumber,
+        value: U7) {
+        (self.control_change_callback)(timestamp, midi_channel, control_change, value);
+    }
+
+    fn system_exclusive(&mut self, timestamp: u64, global_channel: GlobalChannel,
+        command_value: u8, function_id: u8, data: &[u8]) {
+        (self.system_exclusive_callback)(timestamp, global_channel, command_value, function_id,
+            data);
+    }
+}
+
+type SharedOutput = Arc<Mutex<Option<MidiOutputConnection>>>;
+
 pub struct Connection {
     midi_input_connection: Option<MidiInputConnection<()>>,
-    midi_output_connection: Option<MidiOutputConnection>,
+    midi_output_connection: SharedOutput,
+    output_sender: Option<OutputSender>,
+    global_channel: Arc<AtomicU8>,
+}
+
+impl Default for Connection {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Connection {
     pub fn new() -> Self {
         Connection {
             midi_input_connection: None,
-            midi_output_connection: None,
+            midi_output_connection: Arc::new(Mutex::new(None)),
+            output_sender: None,
+            global_channel: Arc::new(AtomicU8::new(GlobalChannel::MIN.get())),
         }
     }
 
+    /// The global channel system exclusive requests are sent on.
+    pub fn get_global_channel(&self) -> GlobalChannel {
+        GlobalChannel::from_masked(self.global_channel.load(Ordering::Relaxed))
+    }
+
+    pub fn set_global_channel(&mut self, global_channel: GlobalChannel) {
+        self.global_channel.store(global_channel.get(), Ordering::Relaxed);
+    }
+
+    /// A cloneable sender writing to this connection's output from a background thread, which
+    /// can be moved into input callbacks. It stays valid across `close` and `open`.
+    pub fn get_output_sender(&mut self) -> OutputSender {
+        let midi_output_connection = &self.midi_output_connection;
+        let global_channel = &self.global_channel;
+        self.output_sender.get_or_insert_with(|| {
+            OutputSender::spawn(midi_output_connection.clone(), global_channel.clone())
+        }).clone()
+    }
+
     pub fn open<F, G>(
         &mut self,
-        mut control_change_callback: F,
-        mut system_exclusive_callback: G,
+        control_change_callback: F,
+        system_exclusive_callback: G,
     ) -> Result<()> where
-        F: FnMut(u64, u8, u8, u8) + Send + 'static,
-        G: FnMut(u64, u8, u8, u8, &[u8]) + Send + 'static {
+        F: FnMut(u64, Channel, CcNumber, U7) + Send + 'static,
+        G: FnMut(u64, GlobalChannel, u8, u8, &[u8]) + Send + 'static {
+
+        self.open_with_handler(CallbackHandler {
+            control_change_callback,
+            system_exclusive_callback,
+        })
+    }
+
+    /// Opens the connection, passing every decoded message to `handler`.
+    pub fn open_with_handler<H>(&mut self, mut handler: H) -> Result<()> where
+        H: MessageHandler + Send + 'static {
+
+        self.open_raw(move |timestamp, message| {
+            decode_message(timestamp, message, &mut handler);
+        })
+    }
+
+    /// Opens the connection, passing every incoming message to `raw_callback` undecoded.
+    pub fn open_raw<F>(&mut self, mut raw_callback: F) -> Result<()> where
+        F: FnMut(u64, &[u8]) + Send + 'static {
 
         let midi_input = MidiInput::new("input")?;
         let midi_output = MidiOutput::new("output")?;
@@ -137,210 +245,458 @@ impl Connection {
         };
 
         self.midi_input_connection = midi_input.connect(input_port, "input_port",
-            move |timestamp, message, _| {
-            let mut iter = message.iter().enumerate();
+            move |timestamp, message, _| raw_callback(timestamp, message), ()).ok();
+
+        *lock_output(&self.midi_output_connection) =
+            midi_output.connect(output_port, "output_port").ok();
+
+        Ok(())
+    }
+
+    pub fn close(&mut self) {
+        if let Some(connection) = self.midi_input_connection.take() {
+            connection.close();
+        }
+
+        if let Some(connection) = lock_output(&self.midi_output_connection).take() {
+            connection.close();
+        }
+    }
+
+    pub fn send_message(&mut self, message: &[u8]) -> Result<()> {
+        send_output(&self.midi_output_connection, message)
+    }
+
+    pub fn send_control_change(&mut self, midi_channel: Channel, control_change: CcNumber,
+        value: U7) -> Result<()> {
+        self.send_message(&control_change_message(midi_channel, control_change, value))
+    }
+
+    pub fn send_note(&mut self, midi_channel: Channel, note_number: U7, velocity: U7)
+    -> Result<()> {
+        self.send_message(&note_message(midi_channel, note_number, velocity))
+    }
+
+    /// Sends the universal identity request to all channels. The device answers with a message
+    /// decoded into a `DeviceInfo`.
+    pub fn device_inquiry_request(&mut self) -> Result<()> {
+        self.send_message(&DEVICE_INQUIRY_REQUEST)
+    }
+
+    /// Sends the current scene data dump request on every global channel, for finding the channel
+    /// of a device that does not answer the identity request. The global channel is kept.
+    pub fn probe_global_channels(&mut self) -> Result<()> {
+        let global_channel = self.get_global_channel();
+        let result = (0..16).try_for_each(|n| {
+            self.set_global_channel(GlobalChannel::from_masked(n));
+            self.current_scene_data_dump_request()
+        });
+        self.set_global_channel(global_channel);
+        result
+    }
+
+    pub fn current_scene_data_dump_request(&mut self) -> Result<()> {
+        let message = korg_request(self.get_global_channel(), Command::DataDumpRequest,
+            RequestType::CurrentSceneDataDumpRequest as u8);
+        self.send_message(&message)
+    }
+
+    pub fn scene_write_request(&mut self) -> Result<()> {
+        let message = korg_request(self.get_global_channel(), Command::DataDumpRequest,
+            RequestType::SceneWriteRequest as u8);
+        self.send_message(&message)
+    }
+
+    /// Switches the device into native mode with `IoType::In` and back to normal mode with
+    /// `IoType::Out`. The device acknowledges with a `DeviceResponse::NativeModeInOut`.
+    pub fn native_mode_io_request(&mut self, io_type: IoType) -> Result<()> {
+        self.send_message(&native_mode_io_message(self.get_global_channel(), io_type))
+    }
+
+    /// Asks the device whether it is in normal or native mode. The device answers with a
+    /// `DeviceResponse::ModeData`.
+    pub fn mode_request(&mut self) -> Result<()> {
+        let message = korg_request(self.get_global_channel(), Command::DataDumpRequest,
+            RequestType::ModeRequest as u8);
+        self.send_message(&message)
+    }
+
+    /// Sends a scene to the device. The last byte of `scene_data` holds the end of exclusive
+    /// marker when received from the device, so it is replaced by one here.
+    pub fn current_scene_data_dump(&mut self, scene_data: &[u8; 389]) -> Result<()> {
+        self.send_message(&scene_dump_message(self.get_global_channel(), scene_data))
+    }
+}
+
+// the output is shared with the output thread, which may outlive the connection
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Sends messages through a `Connection`'s output without access to the `Connection`, e.g. from
+/// input callbacks. Messages are queued and written in order by a background thread, which
+/// drops them while the connection is closed.
+#[derive(Debug, Clone)]
+pub struct OutputSender {
+    sender: Sender<Vec<u8>>,
+    global_channel: Arc<AtomicU8>,
+}
+
+impl OutputSender {
+    fn spawn(midi_output_connection: SharedOutput, global_channel: Arc<AtomicU8>) -> Self {
+        let (sender, receiver) = channel::<Vec<u8>>();
+        thread::spawn(move || {
+            // runs until every sender, including the connection's, is dropped
+            for message in receiver {
+                send_output(&midi_output_connection, &message).ok();
+            }
+        });
+        OutputSender { sender, global_channel }
+    }
+
+    /// The global channel of the connection, followed as it changes.
+    pub fn get_global_channel(&self) -> GlobalChannel {
+        GlobalChannel::from_masked(self.global_channel.load(Ordering::Relaxed))
+    }
+
+    /// Queues a message. Fails only when the output thread has stopped.
+    pub fn send_message(&self, message: &[u8]) -> Result<()> {
+        self.sender.send(message.to_vec()).map_err(|_| Error::ConnectionClosed)
+    }
+
+    pub fn send_control_change(&self, midi_channel: Channel, control_change: CcNumber,
+        value: U7) -> Result<()> {
+        self.send_message(&control_change_message(midi_channel, control_change, value))
+    }
+
+    pub fn send_note(&self, midi_channel: Channel, note_number: U7, velocity: U7) -> Result<()> {
+        self.send_message(&note_message(midi_channel, note_number, velocity))
+    }
 
+    /// Sends the value turning a button's LED on or off, as assigned in `parameters`.
+    pub fn set_button_led(&self, parameters: &Parameters, control: ControlId, on: bool)
+    -> Result<()> {
+        let (button_parameters, value) = match (parameters.get_button_parameters(control),
+            parameters.get_button_value(control, on)) {
+            (Some(button_parameters), Some(value)) => (button_parameters, value),
+            _ => return Ok(()),
+        };
+        let midi_channel = parameters.get_control_channel(control);
+
+        match button_parameters.assign_type {
+            ButtonAssignType::ControlChange => self.send_control_change(midi_channel,
+                button_parameters.note_number.into(), value),
+            ButtonAssignType::Note =>
+                self.send_note(midi_channel, button_parameters.note_number, value),
+            ButtonAssignType::NoAssign => Ok(()),
+        }
+    }
+
+    pub fn device_inquiry_request(&self) -> Result<()> {
+        self.send_message(&DEVICE_INQUIRY_REQUEST)
+    }
+
+    pub fn current_scene_data_dump_request(&self) -> Result<()> {
+        self.send_message(&korg_request(self.get_global_channel(), Command::DataDumpRequest,
+            RequestType::CurrentSceneDataDumpRequest as u8))
+    }
+
+    pub fn scene_write_request(&self) -> Result<()> {
+        self.send_message(&korg_request(self.get_global_channel(), Command::DataDumpRequest,
+            RequestType::SceneWriteRequest as u8))
+    }
+
+    pub fn native_mode_io_request(&self, io_type: IoType) -> Result<()> {
+        self.send_message(&native_mode_io_message(self.get_global_channel(), io_type))
+    }
+
+    pub fn mode_request(&self) -> Result<()> {
+        self.send_message(&korg_request(self.get_global_channel(), Command::DataDumpRequest,
+            RequestType::ModeRequest as u8))
+    }
+
+    pub fn current_scene_data_dump(&self, scene_data: &[u8; 389]) -> Result<()> {
+        self.send_message(&scene_dump_message(self.get_global_channel(), scene_data))
+    }
+}
+
+const DEVICE_INQUIRY_REQUEST: [u8; 6] = [0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7];
+
+fn lock_output(midi_output_connection: &SharedOutput)
+-> MutexGuard<'_, Option<MidiOutputConnection>> {
+    midi_output_connection.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn send_output(midi_output_connection: &SharedOutput, message: &[u8]) -> Result<()> {
+    match &mut *lock_output(midi_output_connection) {
+        Some(connection) => {
+            connection.send(message)?;
+            Ok(())
+        },
+        None => Err(Error::ConnectionClosed),
+    }
+}
+
+fn control_change_message(midi_channel: Channel, control_change: CcNumber, value: U7) -> [u8; 3] {
+    [0xB0 | midi_channel.get(), control_change.get(), value.get()]
+}
+
+/// A Note On message, or a Note Off message for a velocity of 0.
+fn note_message(midi_channel: Channel, note_number: U7, velocity: U7) -> [u8; 3] {
+    match velocity.get() {
+        0 => [0x80 | midi_channel.get(), note_number.get(), 0],
+        velocity => [0x90 | midi_channel.get(), note_number.get(), velocity],
+    }
+}
+
+fn korg_request(global_channel: GlobalChannel, command: Command, function: u8) -> [u8; 11] {
+    [
+        0xF0, 0x42, 0x40 | global_channel.get(),
+        0x00, 0x01, 0x13, 0x00,
+        command as u8,
+        function,
+        0x00,
+        0xF7,
+    ]
+}
+
+fn native_mode_io_message(global_channel: GlobalChannel, io_type: IoType) -> [u8; 11] {
+    [
+        0xF0, 0x42, 0x40 | global_channel.get(),
+        0x00, 0x01, 0x13, 0x00,
+        Command::NativeModeInOutRequest as u8,
+        0x00,
+        io_type as u8,
+        0xF7,
+    ]
+}
+
+/// `F0 42 4g 00 01 13 00 7F 7F 02 03 05 40 <388 data bytes> F7`: the data dump command with the
+/// size of 389 in two 7-bit bytes, then the current scene data dump function and the scene.
+fn scene_dump_message(global_channel: GlobalChannel, scene_data: &[u8; 389]) -> [u8; 402] {
+    let mut message: [u8; 402] = [0; 402];
+    message[0] = 0xF0;
+    message[1] = 0x42;
+    message[2] = 0x40 | global_channel.get();
+    message[3] = 0x00;
+    message[4] = 0x01;
+    message[5] = 0x13;
+    message[6] = 0x00;
+    message[7] = 0x7F;
+    message[8] = 0x7F;
+    message[9] = 0x02;
+    message[10] = 0x03;
+    message[11] = 0x05;
+    message[12] = 0x40;
+    message[13..401].clone_from_slice(&scene_data[..388]);
+    message[401] = 0xF7;
+    message
+}
+
+/// Decodes a message received from the device, passing channel voice messages and Korg system
+/// exclusive messages to the matching `handler` method. Anything else is ignored.
+pub fn decode_message<H: MessageHandler>(timestamp: u64, message: &[u8], handler: &mut H) {
+    let mut iter = message.iter().enumerate();
+
+    match iter.next() {
+        Some((_, &n)) if n & 0b1111_0000 == 0xB0 => {
+            let midi_channel = Channel::from_masked(n);
+            let control_change = match iter.next() {
+                Some((_, &m)) => CcNumber::from_masked(m),
+                None => return,
+            };
+            let value = match iter.next() {
+                Some((_, &m)) => U7::from_masked(m),
+                None => return,
+            };
+            handler.control_change(timestamp, midi_channel, control_change, value);
+        },
+        Some((_, &n)) if n & 0b1110_0000 == 0x80 => {
+            let midi_channel = Channel::from_masked(n);
+            let note_number = match iter.next() {
+                Some((_, &m)) => U7::from_masked(m),
+                None => return,
+            };
+            let velocity = match iter.next() {
+                Some((_, &m)) => U7::from_masked(m),
+                None => return,
+            };
+            match n & 0b1111_0000 == 0x90 && velocity != U7::MIN {
+                true => handler.note_on(timestamp, midi_channel, note_number, velocity),
+                false => handler.note_off(timestamp, midi_channel, note_number, velocity),
+            }
+        },
+        Some((_, &n)) if (0x80..0xF0).contains(&n) => handler.channel_message(timestamp, message),
+        Some((_, &0xF0)) if message.get(1) == Some(&0x7E) => {
+            if let Some(device_info) = DeviceInfo::parse(message) {
+                handler.device_info(timestamp, device_info);
+            }
+        },
+        Some((_, &0xF0)) => {
             match iter.next() {
-                Some((_, &n)) if n & 0b1111_0000 == 0xB0 => {
-                    let midi_channel = n & 0b0000_1111;
-                    let control_change = match iter.next() {
-                        Some((_, &m)) => m,
-                        None => return,
-                    };
-                    let value = match iter.next() {
-                        Some((_, &m)) => m,
-                        None => return,
-                    };
-                    control_change_callback(timestamp, midi_channel, control_change, value);
-                },
-                Some((_, &0xF0)) => {
-                    match iter.next() {
-                        Some((_, &0x42)) => (),
-                        _ => return,
-                    };
+                Some((_, &0x42)) => (),
+                _ => return,
+            };
 
-                    let global_channel = match iter.next() {
-                        Some((_, &n)) if n & 0b1111_0000 == 0x40 => n & 0b0000_1111,
-                        _ => return,
-                    };
+            let global_channel = match iter.next() {
+                Some((_, &n)) if n & 0b1111_0000 == 0x40 => GlobalChannel::from_masked(n),
+                _ => return,
+            };
 
-                    let (command_index, command_value) = match iter.nth(4) {
-                        Some((i, &n)) => (i, n),
-                        None => return, 
-                    };
+            let (_, command_value) = match iter.nth(4) {
+                Some((i, &n)) => (i, n),
+                None => return, 
+            };
 
-                    // let function_id = match iter.next() {
-                    //     Some((_, &n)) => n,
-                    //     None => return,
-                    // };
-
-                    let (function_id, data): (u8, &[u8]) =
-                        match command_value & 0b0010_0000 == 0x00 {
-                        true => match iter.next() {
-                            Some((_, &n)) => (n, &[]),
-                            None => return,
-                        }
-                        false => {
-                            let (data_start_index, num_data): (usize, usize) = match iter.next() {
-                                Some((_, &0x7F)) => {
-                                    match iter.next() {
-                                        Some((_, &0x02)) => (),
-                                        _ => return,
-                                    };
-                                    let msb: usize = match iter.next() {
-                                        Some((_, &n)) => n as usize,
-                                        None => return,
-                                    };
-                                    let (index, lsb): (_, usize) = match iter.next() {
-                                        Some((i, &n)) => (i, n as usize),
-                                        None => return,
-                                    };
-                                    let num = (msb << 7) | lsb;
-                                    (index + 2, num)
-                                },
-                                Some((i, _)) => (i + 2, 1),
+            // let function_id = match iter.next() {
+            //     Some((_, &n)) => n,
+            //     None => return,
+            // };
+
+            let (function_id, data): (u8, &[u8]) =
+                match command_value & 0b0010_0000 == 0x00 {
+                true => match iter.next() {
+                    Some((i, &n)) => (n, &message[i + 1..]),
+                    None => return,
+                }
+                false => {
+                    let (data_start_index, num_data): (usize, usize) = match iter.next() {
+                        Some((_, &0x7F)) => {
+                            match iter.next() {
+                                Some((_, &0x02)) => (),
+                                _ => return,
+                            };
+                            let msb: usize = match iter.next() {
+                                Some((_, &n)) => n as usize,
                                 None => return,
                             };
-                            let function_id = match iter.next() {
-                                Some((_, &n)) => n,
+                            let (index, lsb): (_, usize) = match iter.next() {
+                                Some((i, &n)) => (i, n as usize),
                                 None => return,
                             };
-                            if message.len() < data_start_index + num_data {
-                                return;
-                            }
-                            (function_id, &message[data_start_index..data_start_index + num_data])
+                            let num = (msb << 7) | lsb;
+                            (index + 2, num)
                         },
+                        Some((i, _)) => (i + 2, 1),
+                        None => return,
                     };
-
-                    if data.last() != Some(&0xF7) {
+                    let function_id = match iter.next() {
+                        Some((_, &n)) => n,
+                        None => return,
+                    };
+                    if message.len() < data_start_index + num_data {
                         return;
                     }
-
-                    system_exclusive_callback(timestamp, global_channel, command_value, function_id,
-                        data);
+                    (function_id, &message[data_start_index..data_start_index + num_data])
                 },
-                _ => (),
             };
-        }, ()).ok();
 
-        self.midi_output_connection = midi_output.connect(output_port, "output_port").ok();
+            if data.last() != Some(&0xF7) {
+                return;
+            }
 
-        Ok(())
-    }
+            handler.system_exclusive(timestamp, global_channel, command_value, function_id, data);
+            if let Some(response) = DeviceResponse::parse(command_value, function_id, data) {
+                handler.device_response(timestamp, global_channel, response);
+            }
+        },
+        _ => (),
+    };
+}
 
-    pub fn close(&mut self) {
-        let midi_input_connection = self.midi_input_connection.take();
-        match midi_input_connection {
-            Some(connection) => {
-                connection.close();
-            },
-            None => (),
-        };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let midi_output_connection = self.midi_output_connection.take();
-        match midi_output_connection {
-            Some(connection) => {
-                connection.close();
-            },
-            None => (),
-        };
+    #[derive(Debug, PartialEq, Eq)]
+    enum Decoded {
+        NoteOn(Channel, U7, U7),
+        NoteOff(Channel, U7, U7),
+        ChannelMessage(Vec<u8>),
     }
 
-    pub fn current_scene_data_dump_request(&mut self, global_channel: u8) -> Result<()> {
-        match &mut self.midi_output_connection {
-            Some(connection) => {
-                let message: [u8; 11] = [
-                    0xF0, 0x42, 0x40 | global_channel,
-                    0x00, 0x01, 0x13, 0x00,  
-                    0x1F,
-                    0x10,
-                    0x00,
-                    0xF7,
-                ];
-                connection.send(&message)?;
-                Ok(())
-            },
-            None => Err(Error::ConnectionClosed),
+    #[derive(Default)]
+    struct Recorder(Vec<Decoded>);
+
+    impl MessageHandler for Recorder {
+        fn note_on(&mut self, _timestamp: u64, midi_channel: Channel, note_number: U7,
+            velocity: U7) {
+            self.0.push(Decoded::NoteOn(midi_channel, note_number, velocity));
+        }
+
+        fn note_off(&mut self, _timestamp: u64, midi_channel: Channel, note_number: U7,
+            velocity: U7) {
+            self.0.push(Decoded::NoteOff(midi_channel, note_number, velocity));
         }
-    }
 
-    fn scene_write_request(&mut self, global_channel: u8) -> Result<()> {
-        match &mut self.midi_output_connection {
-            Some(connection) => {
-                let message: [u8; 11] = [
-                    0xF0, 0x42, 0x40 | global_channel,
-                    0x00, 0x01, 0x13, 0x00,  
-                    0x1F,
-                    0x11,
-                    0x00,
-                    0xF7,
-                ];
-                connection.send(&message)?;
-                Ok(())
-            },
-            None => Err(Error::ConnectionClosed),
+        fn channel_message(&mut self, _timestamp: u64, message: &[u8]) {
+            self.0.push(Decoded::ChannelMessage(message.to_vec()));
         }
     }
 
-    fn native_mode_io_request(&mut self, global_channel: u8, io_type: IoType) -> Result<()> {
-        match &mut self.midi_output_connection {
-            Some(connection) => {
-                let message: [u8; 11] = [
-                    0xF0, 0x42, 0x40 | global_channel,
-                    0x00, 0x01, 0x13, 0x00,  
-                    0x00,
-                    0x00,
-                    io_type as u8,
-                    0xF7,
-                ];
-                connection.send(&message)?;
-                Ok(())
-            },
-            None => Err(Error::ConnectionClosed),
+    fn decode(message: &[u8]) -> Vec<Decoded> {
+        let mut recorder = Recorder::default();
+        decode_message(0, message, &mut recorder);
+        recorder.0
+    }
+
+    fn note(midi_channel: u8, note_number: u8, velocity: u8) -> (Channel, U7, U7) {
+        (Channel::from_const(midi_channel), U7::from_const(note_number), U7::from_const(velocity))
+    }
+
+    #[test]
+    fn decode_note_on() {
+        let (midi_channel, note_number, velocity) = note(2, 60, 100);
+        assert_eq!(decode(&[0x92, 60, 100]),
+            vec![Decoded::NoteOn(midi_channel, note_number, velocity)]);
+    }
+
+    #[test]
+    fn decode_note_on_without_velocity_as_note_off() {
+        let (midi_channel, note_number, velocity) = note(0, 60, 0);
+        assert_eq!(decode(&[0x90, 60, 0]),
+            vec![Decoded::NoteOff(midi_channel, note_number, velocity)]);
+    }
+
+    #[test]
+    fn decode_note_off() {
+        let (midi_channel, note_number, velocity) = note(15, 36, 64);
+        assert_eq!(decode(&[0x8F, 36, 64]),
+            vec![Decoded::NoteOff(midi_channel, note_number, velocity)]);
+    }
+
+    #[test]
+    fn decode_other_channel_messages() {
+        for message in [&[0xE0, 0x00, 0x40][..], &[0xD3, 0x20], &[0xA1, 60, 10], &[0xC5, 7]] {
+            assert_eq!(decode(message), vec![Decoded::ChannelMessage(message.to_vec())]);
         }
     }
 
-    fn mode_request(&mut self, global_channel: u8, io_type: IoType) -> Result<()> {
-        match &mut self.midi_output_connection {
-            Some(connection) => {
-                let message: [u8; 11] = [
-                    0xF0, 0x42, 0x40 | global_channel,
-                    0x00, 0x01, 0x13, 0x00,  
-                    0x1F,
-                    0x12,
-                    0x00,
-                    0xF7,
-                ];
-                connection.send(&message)?;
-                Ok(())
-            },
-            None => Err(Error::ConnectionClosed),
+    #[test]
+    fn ignore_truncated_notes() {
+        for message in [&[0x90][..], &[0x90, 60], &[0x80, 60], &[]] {
+            assert_eq!(decode(message), Vec::new());
         }
     }
 
-    fn current_scene_data_dump(&mut self, global_channel: u8, scene_data: &[u8; 389]) -> Result<()> {
-        match &mut self.midi_output_connection {
-            Some(connection) => {
-                let mut message: [u8; 403] = [0; 403];
-                message[0] = 0xF0;
-                message[1] = 0x42;
-                message[2] = 0x40 | global_channel;
-                message[3] = 0x00;
-                message[4] = 0x01;
-                message[5] = 0x13;
-                message[6] = 0x00;
-                message[7] = 0x7F;
-                message[8] = 0x7F;
-                message[9] = 0x02;
-                message[10] = 0x03;
-                message[11] = 0x05;
-                message[12] = 0x40;
-                message[13..402].clone_from_slice(scene_data);
-                message[402] = 0xF7;
-                connection.send(&message)?;
-                Ok(())
-            },
-            None => Err(Error::ConnectionClosed),
+    #[test]
+    fn scene_dump_message_layout() {
+        let mut scene_data = [0u8; 389];
+        for (i, byte) in scene_data.iter_mut().enumerate() {
+            *byte = (i % 0x80) as u8;
         }
+        scene_data[388] = 0xF7;
+
+        let message = scene_dump_message(GlobalChannel::from_const(3), &scene_data);
+        assert_eq!(message.len(), 402);
+        assert_eq!(message[..13], [
+            0xF0, 0x42, 0x43, 0x00, 0x01, 0x13, 0x00,
+            0x7F, 0x7F, 0x02, 0x03, 0x05, 0x40,
+        ]);
+        assert_eq!(message[13..401], scene_data[..388]);
+        assert_eq!(message[401], 0xF7);
+        assert_eq!(message.iter().filter(|&&byte| byte == 0xF7).count(), 1);
     }
 }
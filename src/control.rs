@@ -0,0 +1,177 @@
+use std::fmt;
+
+use super::error::Error;
+use super::{Result, TransportButton};
+
+/// Any physical control on the device. Strip controls carry their group index, 0-7.
+///
+/// Indices from 8 up do not name a control, and methods taking a `ControlId` may panic on them or
+/// mix them up with other controls. The checked constructors, such as `ControlId::slider`, reject
+/// them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ControlId {
+    Slider(u8),
+    Knob(u8),
+    Solo(u8),
+    Mute(u8),
+    Record(u8),
+    Transport(TransportButton),
+}
+
+/// Where a control sits on the panel, on a grid counted from the top left.
+///
+/// The transport section spans columns 0-4. Each group then takes two columns: the knob and the
+/// solo, mute and record buttons stacked in the first one, the slider next to the buttons in the
+/// second one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PanelPosition {
+    pub column: u8,
+    pub row: u8,
+}
+
+impl ControlId {
+    /// Every control, strip by strip kind (sliders, knobs, solo, mute and record buttons) and then
+    /// the transport buttons in panel order.
+    pub const ALL: [ControlId; 51] = all_controls();
+
+    pub fn iter() -> impl Iterator<Item = ControlId> {
+        Self::ALL.iter().copied()
+    }
+
+    pub fn slider(group_index: usize) -> Result<Self> {
+        check_group_index(group_index).map(ControlId::Slider)
+    }
+
+    pub fn knob(group_index: usize) -> Result<Self> {
+        check_group_index(group_index).map(ControlId::Knob)
+    }
+
+    pub fn solo(group_index: usize) -> Result<Self> {
+        check_group_index(group_index).map(ControlId::Solo)
+    }
+
+    pub fn mute(group_index: usize) -> Result<Self> {
+        check_group_index(group_index).map(ControlId::Mute)
+    }
+
+    pub fn record(group_index: usize) -> Result<Self> {
+        check_group_index(group_index).map(ControlId::Record)
+    }
+
+    /// Whether the control exists, i.e. its group index, if any, is 0-7.
+    pub fn is_valid(&self) -> bool {
+        self.get_group_index().is_none_or(|group_index| group_index < 8)
+    }
+
+    /// The position of the control in `ControlId::ALL`.
+    pub fn get_index(&self) -> usize {
+        debug_assert!(self.is_valid(), "{:?} has no group index 0-7", self);
+        match *self {
+            ControlId::Slider(i) => i as usize,
+            ControlId::Knob(i)   => 8 + i as usize,
+            ControlId::Solo(i)   => 16 + i as usize,
+            ControlId::Mute(i)   => 24 + i as usize,
+            ControlId::Record(i) => 32 + i as usize,
+            ControlId::Transport(button_type) => 40 + button_type as usize,
+        }
+    }
+
+    pub fn is_button(&self) -> bool {
+        !matches!(self, ControlId::Slider(_) | ControlId::Knob(_))
+    }
+
+    /// The group the control belongs to, `None` for transport buttons.
+    pub fn get_group_index(&self) -> Option<usize> {
+        match *self {
+            ControlId::Slider(i) | ControlId::Knob(i) | ControlId::Solo(i) | ControlId::Mute(i)
+                | ControlId::Record(i) => Some(i as usize),
+            ControlId::Transport(_) => None,
+        }
+    }
+
+    pub fn get_position(&self) -> PanelPosition {
+        let (column, row) = match *self {
+            ControlId::Knob(i)   => (5 + i * 2, 0),
+            ControlId::Solo(i)   => (5 + i * 2, 1),
+            ControlId::Mute(i)   => (5 + i * 2, 2),
+            ControlId::Record(i) => (5 + i * 2, 3),
+            ControlId::Slider(i) => (6 + i * 2, 1),
+            ControlId::Transport(button_type) => match button_type {
+                TransportButton::TrackRewind       => (0, 0),
+                TransportButton::TrackFastforward  => (1, 0),
+                TransportButton::Cycle             => (0, 1),
+                TransportButton::Set               => (2, 1),
+                TransportButton::MarkerRewind      => (3, 1),
+                TransportButton::MarkerFastforward => (4, 1),
+                TransportButton::Rewind            => (0, 2),
+                TransportButton::Fastforward       => (1, 2),
+                TransportButton::Stop              => (2, 2),
+                TransportButton::Play              => (3, 2),
+                TransportButton::Record            => (4, 2),
+            },
+        };
+        PanelPosition { column, row }
+    }
+}
+
+/// Names the control as printed on the panel, numbering groups from 1, e.g. "Slider 1".
+impl fmt::Display for ControlId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ControlId::Slider(i) => write!(f, "Slider {}", i + 1),
+            ControlId::Knob(i)   => write!(f, "Knob {}", i + 1),
+            ControlId::Solo(i)   => write!(f, "Solo {}", i + 1),
+            ControlId::Mute(i)   => write!(f, "Mute {}", i + 1),
+            ControlId::Record(i) => write!(f, "Record {}", i + 1),
+            ControlId::Transport(button_type) => f.write_str(button_type.get_name()),
+        }
+    }
+}
+
+fn check_group_index(group_index: usize) -> Result<u8> {
+    match group_index {
+        0..=7 => Ok(group_index as u8),
+        _ => Err(Error::InvalidGroupIndex(group_index)),
+    }
+}
+
+const fn all_controls() -> [ControlId; 51] {
+    let mut controls = [ControlId::Slider(0); 51];
+    let mut i = 0;
+    while i < 8 {
+        controls[i] = ControlId::Slider(i as u8);
+        controls[8 + i] = ControlId::Knob(i as u8);
+        controls[16 + i] = ControlId::Solo(i as u8);
+        controls[24 + i] = ControlId::Mute(i as u8);
+        controls[32 + i] = ControlId::Record(i as u8);
+        i += 1;
+    }
+    let mut j = 0;
+    while j < 11 {
+        controls[40 + j] = ControlId::Transport(TransportButton::ALL[j]);
+        j += 1;
+    }
+    controls
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checked_constructors() {
+        assert_eq!(ControlId::slider(7).ok(), Some(ControlId::Slider(7)));
+        assert_eq!(ControlId::record(0).ok(), Some(ControlId::Record(0)));
+        assert!(matches!(ControlId::slider(8), Err(Error::InvalidGroupIndex(8))));
+        assert!(matches!(ControlId::knob(usize::MAX), Err(Error::InvalidGroupIndex(_))));
+        assert!(!ControlId::Slider(8).is_valid());
+    }
+
+    #[test]
+    fn indices_follow_all() {
+        for (index, control) in ControlId::iter().enumerate() {
+            assert!(control.is_valid());
+            assert_eq!(control.get_index(), index);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use super::types::U7;
+
+/// Response curve applied to a normalized (`0.0..=1.0`) control value.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ValueCurve {
+    #[default]
+    Linear,
+    Logarithmic,
+    Exponential,
+    SCurve,
+    /// User supplied lookup table, linearly interpolated between evenly spaced entries.
+    Lookup(Vec<f32>),
+}
+
+impl ValueCurve {
+    pub fn apply(&self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            ValueCurve::Linear => x,
+            ValueCurve::Logarithmic => (1.0 + 9.0 * x).log10(),
+            ValueCurve::Exponential => (10f32.powf(x) - 1.0) / 9.0,
+            ValueCurve::SCurve => x * x * (3.0 - 2.0 * x),
+            ValueCurve::Lookup(table) => match table.len() {
+                0 => x,
+                1 => table[0],
+                len => {
+                    let position = x * (len - 1) as f32;
+                    let index = (position as usize).min(len - 2);
+                    let fraction = position - index as f32;
+                    table[index] + (table[index + 1] - table[index]) * fraction
+                },
+            },
+        }
+    }
+}
+
+/// Maps a raw control value to the value exposed by the high-level API.
+///
+/// The raw value is first normalized against the control's `min_value..max_value`, then shaped by
+/// `curve`, optionally inverted, and finally scaled into `output_min..output_max`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueMapping {
+    pub curve: ValueCurve,
+    pub inverted: bool,
+    pub output_min: f32,
+    pub output_max: f32,
+}
+
+impl Default for ValueMapping {
+    fn default() -> Self {
+        ValueMapping {
+            curve: ValueCurve::Linear,
+            inverted: false,
+            output_min: 0.0,
+            output_max: 1.0,
+        }
+    }
+}
+
+impl ValueMapping {
+    pub fn apply(&self, normalized: f32) -> f32 {
+        let shaped = self.curve.apply(normalized);
+        let shaped = if self.inverted { 1.0 - shaped } else { shaped };
+        self.output_min + (self.output_max - self.output_min) * shaped
+    }
+}
+
+/// Normalizes `value` against `min_value..max_value`, clamping values outside of the range.
+///
+/// An inverted range (`min_value > max_value`) yields a descending response, matching how the
+/// device itself treats such a range.
+pub fn normalize(value: U7, min_value: U7, max_value: U7) -> f32 {
+    let (value, min_value, max_value) = (value.get(), min_value.get(), max_value.get());
+    let (low, high) = match min_value <= max_value {
+        true => (min_value, max_value),
+        false => (max_value, min_value),
+    };
+
+    let range = high - low;
+    if range == 0 {
+        return 0.0;
+    }
+
+    let normalized = (value.clamp(low, high) - low) as f32 / range as f32;
+    match min_value <= max_value {
+        true => normalized,
+        false => 1.0 - normalized,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(value: u8, min_value: u8, max_value: u8) -> f32 {
+        super::normalize(U7::from_const(value), U7::from_const(min_value),
+            U7::from_const(max_value))
+    }
+
+    #[test]
+    fn normalize_range() {
+        assert_eq!(normalize(0, 0, 127), 0.0);
+        assert_eq!(normalize(127, 0, 127), 1.0);
+        assert_eq!(normalize(60, 10, 110), 0.5);
+    }
+
+    #[test]
+    fn normalize_inverted_range() {
+        assert_eq!(normalize(110, 110, 10), 0.0);
+        assert_eq!(normalize(10, 110, 10), 1.0);
+        assert_eq!(normalize(60, 110, 10), 0.5);
+    }
+
+    #[test]
+    fn normalize_clamps_values_outside_the_range() {
+        assert_eq!(normalize(0, 10, 110), 0.0);
+        assert_eq!(normalize(127, 10, 110), 1.0);
+        assert_eq!(normalize(0, 110, 10), 1.0);
+        assert_eq!(normalize(127, 110, 10), 0.0);
+    }
+
+    #[test]
+    fn normalize_zero_range() {
+        assert_eq!(normalize(0, 64, 64), 0.0);
+        assert_eq!(normalize(64, 64, 64), 0.0);
+        assert_eq!(normalize(127, 64, 64), 0.0);
+    }
+}
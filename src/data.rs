@@ -1,32 +1,34 @@
 use super::TransportButton;
+use super::control::ControlId;
+use super::types::U7;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
 pub struct GroupData {
-    pub slider_value: u8,
-    pub knob_value: u8,
-    pub solo: u8,
-    pub mute: u8,
-    pub record: u8,
+    pub slider_value: U7,
+    pub knob_value: U7,
+    pub solo: U7,
+    pub mute: U7,
+    pub record: U7,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Data {
-    pub track_rewind: u8,
-    pub track_fastforward: u8,
-    pub cycle: u8,
-    pub set: u8,
-    pub marker_rewind: u8,
-    pub marker_fastforward: u8,
-    pub rewind: u8,
-    pub fastforward: u8,
-    pub stop: u8,
-    pub play: u8,
-    pub record: u8,
+    pub track_rewind: U7,
+    pub track_fastforward: U7,
+    pub cycle: U7,
+    pub set: U7,
+    pub marker_rewind: U7,
+    pub marker_fastforward: U7,
+    pub rewind: U7,
+    pub fastforward: U7,
+    pub stop: U7,
+    pub play: U7,
+    pub record: U7,
     pub groups: [GroupData; 8],
 }
 
 impl Data {
-    pub fn get_transport_button_value(&self, button_type: TransportButton) -> u8 {
+    pub fn get_transport_button_value(&self, button_type: TransportButton) -> U7 {
         match button_type {
             TransportButton::TrackRewind       => self.track_rewind,
             TransportButton::TrackFastforward  => self.track_fastforward,
@@ -41,4 +43,72 @@ impl Data {
             TransportButton::Record            => self.record,
         }
     }
+
+    pub fn set_transport_button_value(&mut self, button_type: TransportButton, value: U7) {
+        let field = match button_type {
+            TransportButton::TrackRewind       => &mut self.track_rewind,
+            TransportButton::TrackFastforward  => &mut self.track_fastforward,
+            TransportButton::Cycle             => &mut self.cycle,
+            TransportButton::Set               => &mut self.set,
+            TransportButton::MarkerRewind      => &mut self.marker_rewind,
+            TransportButton::MarkerFastforward => &mut self.marker_fastforward,
+            TransportButton::Rewind            => &mut self.rewind,
+            TransportButton::Fastforward       => &mut self.fastforward,
+            TransportButton::Stop              => &mut self.stop,
+            TransportButton::Play              => &mut self.play,
+            TransportButton::Record            => &mut self.record,
+        };
+        *field = value;
+    }
+
+    /// Panics if the control is not valid, see `ControlId::is_valid`.
+    pub fn get_value(&self, control: ControlId) -> U7 {
+        match control {
+            ControlId::Slider(i) => self.groups[i as usize].slider_value,
+            ControlId::Knob(i)   => self.groups[i as usize].knob_value,
+            ControlId::Solo(i)   => self.groups[i as usize].solo,
+            ControlId::Mute(i)   => self.groups[i as usize].mute,
+            ControlId::Record(i) => self.groups[i as usize].record,
+            ControlId::Transport(button_type) => self.get_transport_button_value(button_type),
+        }
+    }
+
+    pub fn set_value(&mut self, control: ControlId, value: U7) {
+        match control {
+            ControlId::Slider(i) => self.groups[i as usize].slider_value = value,
+            ControlId::Knob(i)   => self.groups[i as usize].knob_value = value,
+            ControlId::Solo(i)   => self.groups[i as usize].solo = value,
+            ControlId::Mute(i)   => self.groups[i as usize].mute = value,
+            ControlId::Record(i) => self.groups[i as usize].record = value,
+            ControlId::Transport(button_type) => self.set_transport_button_value(button_type, value),
+        }
+    }
+}
+
+/// The values of every control along with the timestamp each one was last received at, in the
+/// microseconds reported by midir.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub data: Data,
+    pub timestamps: [Option<u64>; 51],
+}
+
+impl Default for Snapshot {
+    fn default() -> Self {
+        Snapshot {
+            data: Data::default(),
+            timestamps: [None; 51],
+        }
+    }
+}
+
+impl Snapshot {
+    pub fn get_value(&self, control: ControlId) -> U7 {
+        self.data.get_value(control)
+    }
+
+    /// When the control's value was last received, `None` if it was never received.
+    pub fn get_timestamp(&self, control: ControlId) -> Option<u64> {
+        self.timestamps[control.get_index()]
+    }
 }
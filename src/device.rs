@@ -0,0 +1,291 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use super::connection::{Command, Function};
+use super::types::GlobalChannel;
+use super::{KorgNanokontrol2, Result};
+
+pub const KORG_ID: u8 = 0x42;
+pub const NANOKONTROL2_FAMILY: u16 = 0x0113;
+
+/// The firmware version reported in a device inquiry reply.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FirmwareVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{:02}", self.major, self.minor)
+    }
+}
+
+/// A reply to the universal identity request.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceInfo {
+    /// The global channel the device answered on.
+    pub global_channel: GlobalChannel,
+    pub manufacturer: u8,
+    pub family: u16,
+    pub member: u16,
+    pub version: FirmwareVersion,
+}
+
+impl DeviceInfo {
+    /// Parses an identity reply, `F0 7E 0g 06 02 42 13 01 00 00 mm mm nn nn F7`, with the family,
+    /// member and version numbers sent least significant byte first.
+    pub fn parse(message: &[u8]) -> Option<Self> {
+        match message {
+            &[0xF0, 0x7E, global_channel, 0x06, 0x02, manufacturer, family_lsb, family_msb,
+                member_lsb, member_msb, minor_lsb, minor_msb, major_lsb, major_msb, 0xF7] =>
+                Some(DeviceInfo {
+                    global_channel: GlobalChannel::new(global_channel).ok()?,
+                    manufacturer,
+                    family: join_bytes(family_lsb, family_msb),
+                    member: join_bytes(member_lsb, member_msb),
+                    version: FirmwareVersion {
+                        major: join_bytes(major_lsb, major_msb),
+                        minor: join_bytes(minor_lsb, minor_msb),
+                    },
+                }),
+            _ => None,
+        }
+    }
+
+    pub fn is_nanokontrol2(&self) -> bool {
+        self.manufacturer == KORG_ID && self.family == NANOKONTROL2_FAMILY
+    }
+}
+
+fn join_bytes(lsb: u8, msb: u8) -> u16 {
+    ((msb as u16) << 8) | lsb as u16
+}
+
+/// Whether the device runs its scene (normal mode) or is driven by the host (native mode).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DeviceMode {
+    Normal = 0x00,
+    Native = 0x01,
+}
+
+impl DeviceMode {
+    pub fn try_parse(n: u8) -> Option<Self> {
+        match n {
+            0x00 => Some(DeviceMode::Normal),
+            0x01 => Some(DeviceMode::Native),
+            _ => None,
+        }
+    }
+
+    /// Parses the byte of a native mode acknowledgement, which uses `02` (out) and `03` (in)
+    /// rather than the values of the mode data reply.
+    pub fn try_parse_native_mode_reply(n: u8) -> Option<Self> {
+        match n {
+            0x02 => Some(DeviceMode::Normal),
+            0x03 => Some(DeviceMode::Native),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DeviceMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeviceMode::Normal => f.write_str("normal"),
+            DeviceMode::Native => f.write_str("native"),
+        }
+    }
+}
+
+/// A packet communication reply (command `0x5F`) or native mode acknowledgement (command `0x40`)
+/// from the device.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DeviceResponse {
+    /// The mode the device switched to after a native mode request.
+    NativeModeInOut(DeviceMode),
+    DataLoadCompleted,
+    DataLoadError,
+    WriteCompleted,
+    WriteError,
+    ModeData(DeviceMode),
+    /// A reply with a function ID that is not handled, along with its data.
+    Unknown { function_id: u8, data: Vec<u8> },
+}
+
+impl DeviceResponse {
+    /// Parses a reply from its command, function ID and the data following them, up to the end of
+    /// exclusive marker. Returns `None` for other commands.
+    pub fn parse(command_value: u8, function_id: u8, data: &[u8]) -> Option<Self> {
+        let data = data.strip_suffix(&[0xF7]).unwrap_or(data);
+        match Command::try_parse(command_value) {
+            Some(Command::PacketCommunication) => {
+                let mode = data.first().and_then(|&mode| DeviceMode::try_parse(mode));
+                Some(Self::parse_packet(function_id, data, mode))
+            },
+            Some(Command::NativeModeInOut) => match data.first()
+                .and_then(|&mode| DeviceMode::try_parse_native_mode_reply(mode)) {
+                Some(mode) => Some(DeviceResponse::NativeModeInOut(mode)),
+                None => Some(DeviceResponse::Unknown { function_id, data: data.to_vec() }),
+            },
+            _ => None,
+        }
+    }
+
+    /// The mode reported by a mode data reply or native mode acknowledgement.
+    pub fn get_mode(&self) -> Option<DeviceMode> {
+        match *self {
+            DeviceResponse::ModeData(mode) | DeviceResponse::NativeModeInOut(mode) => Some(mode),
+            _ => None,
+        }
+    }
+
+    fn parse_packet(function_id: u8, data: &[u8], mode: Option<DeviceMode>) -> Self {
+        match (Function::try_parse(function_id), mode) {
+            (Some(Function::DataLoadCompleted), _) => DeviceResponse::DataLoadCompleted,
+            (Some(Function::DataLoadError), _) => DeviceResponse::DataLoadError,
+            (Some(Function::WriteCompleted), _) => DeviceResponse::WriteCompleted,
+            (Some(Function::WriteError), _) => DeviceResponse::WriteError,
+            (Some(Function::ModeData), Some(mode)) => DeviceResponse::ModeData(mode),
+            _ => DeviceResponse::Unknown { function_id, data: data.to_vec() },
+        }
+    }
+}
+
+/// Keeps the device in native mode, switching it back to normal mode when dropped. The device is
+/// reachable through the guard in the meantime.
+pub struct NativeModeGuard<'a> {
+    device: &'a mut KorgNanokontrol2,
+    active: bool,
+}
+
+impl<'a> NativeModeGuard<'a> {
+    pub(crate) fn new(device: &'a mut KorgNanokontrol2) -> Self {
+        NativeModeGuard {
+            device,
+            active: true,
+        }
+    }
+
+    /// Switches back to normal mode, reporting errors that dropping the guard would ignore.
+    pub fn exit(mut self) -> Result<()> {
+        self.active = false;
+        self.device.exit_native_mode()
+    }
+}
+
+impl Deref for NativeModeGuard<'_> {
+    type Target = KorgNanokontrol2;
+
+    fn deref(&self) -> &KorgNanokontrol2 {
+        self.device
+    }
+}
+
+impl DerefMut for NativeModeGuard<'_> {
+    fn deref_mut(&mut self) -> &mut KorgNanokontrol2 {
+        self.device
+    }
+}
+
+impl Drop for NativeModeGuard<'_> {
+    fn drop(&mut self) {
+        if self.active {
+            self.device.exit_native_mode().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::{decode_message, MessageHandler};
+
+    #[derive(Default)]
+    struct Responses(Vec<DeviceResponse>);
+
+    impl MessageHandler for Responses {
+        fn device_response(&mut self, _timestamp: u64, _global_channel: GlobalChannel,
+            response: DeviceResponse) {
+            self.0.push(response);
+        }
+    }
+
+    fn decode_responses(message: &[u8]) -> Vec<DeviceResponse> {
+        let mut responses = Responses::default();
+        decode_message(0, message, &mut responses);
+        responses.0
+    }
+
+    #[test]
+    fn parse_identity_reply() {
+        let device_info = DeviceInfo::parse(&[0xF0, 0x7E, 0x03, 0x06, 0x02, 0x42, 0x13, 0x01,
+            0x00, 0x00, 0x05, 0x00, 0x01, 0x00, 0xF7]).unwrap();
+        assert_eq!(device_info, DeviceInfo {
+            global_channel: GlobalChannel::from_const(3),
+            manufacturer: KORG_ID,
+            family: NANOKONTROL2_FAMILY,
+            member: 0x0000,
+            version: FirmwareVersion { major: 1, minor: 5 },
+        });
+        assert!(device_info.is_nanokontrol2());
+        assert_eq!(device_info.version.to_string(), "1.05");
+    }
+
+    #[test]
+    fn parse_identity_reply_of_the_wrong_length() {
+        let reply = [0xF0, 0x7E, 0x00, 0x06, 0x02, 0x42, 0x13, 0x01, 0x00, 0x00, 0x05, 0x00,
+            0x01, 0x00, 0xF7];
+        assert_eq!(DeviceInfo::parse(&reply[..14]), None);
+        assert_eq!(DeviceInfo::parse(&[&reply[..14], &[0x00, 0xF7]].concat()), None);
+        assert_eq!(DeviceInfo::parse(&[0xF0, 0x7E, 0x00, 0x06, 0x01, 0xF7]), None);
+    }
+
+    #[test]
+    fn parse_other_device() {
+        let device_info = DeviceInfo::parse(&[0xF0, 0x7E, 0x00, 0x06, 0x02, 0x42, 0x11, 0x01,
+            0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0xF7]).unwrap();
+        assert_eq!(device_info.family, 0x0111);
+        assert!(!device_info.is_nanokontrol2());
+    }
+
+    #[test]
+    fn parse_packet_replies() {
+        let parse = |function_id, data: &[u8]| DeviceResponse::parse(0x5F, function_id, data);
+        assert_eq!(parse(0x23, &[0xF7]), Some(DeviceResponse::DataLoadCompleted));
+        assert_eq!(parse(0x24, &[0xF7]), Some(DeviceResponse::DataLoadError));
+        assert_eq!(parse(0x21, &[0xF7]), Some(DeviceResponse::WriteCompleted));
+        assert_eq!(parse(0x22, &[0xF7]), Some(DeviceResponse::WriteError));
+        assert_eq!(parse(0x42, &[0x01, 0xF7]), Some(DeviceResponse::ModeData(DeviceMode::Native)));
+        assert_eq!(parse(0x42, &[0x00]), Some(DeviceResponse::ModeData(DeviceMode::Normal)));
+    }
+
+    #[test]
+    fn parse_unknown_replies() {
+        let parse = |function_id, data: &[u8]| DeviceResponse::parse(0x5F, function_id, data);
+        assert_eq!(parse(0x30, &[0x12, 0x34, 0xF7]),
+            Some(DeviceResponse::Unknown { function_id: 0x30, data: vec![0x12, 0x34] }));
+        assert_eq!(parse(0x42, &[0x05, 0xF7]),
+            Some(DeviceResponse::Unknown { function_id: 0x42, data: vec![0x05] }));
+        assert_eq!(parse(0x42, &[0xF7]),
+            Some(DeviceResponse::Unknown { function_id: 0x42, data: Vec::new() }));
+        assert_eq!(DeviceResponse::parse(0x7F, 0x40, &[0xF7]), None);
+        assert_eq!(DeviceResponse::parse(0x12, 0x23, &[0xF7]), None);
+    }
+
+    #[test]
+    fn decode_packet_reply() {
+        assert_eq!(decode_responses(&[0xF0, 0x42, 0x40, 0x00, 0x01, 0x13, 0x00, 0x5F, 0x23, 0x00,
+            0xF7]), vec![DeviceResponse::DataLoadCompleted]);
+    }
+
+    #[test]
+    fn native_mode_reply() {
+        assert_eq!(decode_responses(&[0xF0, 0x42, 0x40, 0x00, 0x01, 0x13, 0x00, 0x40, 0x00, 0x02,
+            0xF7]), vec![DeviceResponse::NativeModeInOut(DeviceMode::Normal)]);
+        assert_eq!(decode_responses(&[0xF0, 0x42, 0x40, 0x00, 0x01, 0x13, 0x00, 0x40, 0x00, 0x03,
+            0xF7]), vec![DeviceResponse::NativeModeInOut(DeviceMode::Native)]);
+        assert_eq!(DeviceResponse::parse(0x40, 0x00, &[0x01, 0xF7]),
+            Some(DeviceResponse::Unknown { function_id: 0x00, data: vec![0x01] }));
+    }
+}
@@ -1,6 +1,6 @@
-use std::default::Default;
+use super::types::Channel;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum TransportButton {
     TrackRewind,
     TrackFastforward,
@@ -15,17 +15,47 @@ pub enum TransportButton {
     Record,
 }
 
-#[derive(Debug, Copy, Clone)]
+impl TransportButton {
+    pub const ALL: [TransportButton; 11] = [
+        TransportButton::TrackRewind,
+        TransportButton::TrackFastforward,
+        TransportButton::Cycle,
+        TransportButton::Set,
+        TransportButton::MarkerRewind,
+        TransportButton::MarkerFastforward,
+        TransportButton::Rewind,
+        TransportButton::Fastforward,
+        TransportButton::Stop,
+        TransportButton::Play,
+        TransportButton::Record,
+    ];
+
+    /// The button's label on the panel.
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            TransportButton::TrackRewind       => "Track <",
+            TransportButton::TrackFastforward  => "Track >",
+            TransportButton::Cycle             => "Cycle",
+            TransportButton::Set               => "Set",
+            TransportButton::MarkerRewind      => "Marker <",
+            TransportButton::MarkerFastforward => "Marker >",
+            TransportButton::Rewind            => "Rewind",
+            TransportButton::Fastforward       => "Fast Forward",
+            TransportButton::Stop              => "Stop",
+            TransportButton::Play              => "Play",
+            TransportButton::Record            => "Record",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 pub enum ButtonAssignType {
+    #[default]
     NoAssign      = 0,
     ControlChange = 1,
     Note          = 2,
 }
 
-impl Default for ButtonAssignType {
-    fn default() -> Self { ButtonAssignType::NoAssign }
-}
-
 impl From<u8> for ButtonAssignType {
     fn from(n: u8) -> Self {
         match n {
@@ -36,16 +66,13 @@ impl From<u8> for ButtonAssignType {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 pub enum ButtonBehavior {
+    #[default]
     Momentary = 0,
     Toggle    = 1,
 }
 
-impl Default for ButtonBehavior {
-    fn default() -> Self { ButtonBehavior::Momentary }
-}
-
 impl From<u8> for ButtonBehavior {
     fn from(n: u8) -> Self {
         match n {
@@ -55,44 +82,38 @@ impl From<u8> for ButtonBehavior {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 pub enum MidiChannel {
-    Custom(u8),
+    Custom(Channel),
+    #[default]
     Global,
 }
 
-impl Default for MidiChannel {
-    fn default() -> Self { MidiChannel::Global }
-}
-
 impl From<u8> for MidiChannel {
     fn from(n: u8) -> Self {
         match n {
-            k if k < 16 => MidiChannel::Custom(n),
+            k if k < 16 => MidiChannel::Custom(Channel::from_masked(k)),
             _ => MidiChannel::Global,
         }
     }
 }
 
-impl Into<u8> for MidiChannel {
-    fn into(self) -> u8 {
-        match self {
-            MidiChannel::Custom(n) => n,
+impl From<MidiChannel> for u8 {
+    fn from(channel: MidiChannel) -> Self {
+        match channel {
+            MidiChannel::Custom(channel) => channel.get(),
             MidiChannel::Global => 16,
         }
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 pub enum SliderAssignType {
     Disable = 0,
+    #[default]
     Enable  = 1,
 }
 
-impl Default for SliderAssignType {
-    fn default() -> Self { SliderAssignType::Enable }
-}
-
 impl std::convert::From<u8> for SliderAssignType {
     fn from(n: u8) -> Self {
         match n {
@@ -102,8 +123,9 @@ impl std::convert::From<u8> for SliderAssignType {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 pub enum ControlMode {
+    #[default]
     CcMode   = 0,
     Cubase   = 1,
     Dp       = 2,
@@ -112,10 +134,6 @@ pub enum ControlMode {
     Sonar    = 5,
 }
 
-impl Default for ControlMode {
-    fn default() -> Self { ControlMode::CcMode }
-}
-
 impl From<u8> for ControlMode {
     fn from(n: u8) -> Self {
         match n {
@@ -129,16 +147,13 @@ impl From<u8> for ControlMode {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 pub enum LedMode {
+    #[default]
     Internal = 0,
     External = 1,
 }
 
-impl Default for LedMode {
-    fn default() -> Self { LedMode::Internal }
-}
-
 impl From<u8> for LedMode {
     fn from(n: u8) -> Self {
         match n {
@@ -1,5 +1,10 @@
 use std::fmt;
 use std::fmt::Display;
+use std::io;
+use std::sync::Arc;
+
+use super::device::DeviceMode;
+use super::lookup::AmbiguousAssignment;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -16,7 +21,22 @@ pub enum Error {
     InvalidControlMode(u8),
     InvalidLedMode(u8),
     InvalidMidiChannel(u8),
+    InvalidMidiValue(u8),
+    InvalidGroupIndex(usize),
     ConnectionClosed,
+    Io(Arc<io::Error>),
+    InvalidSessionFile(usize),
+    InvalidSceneName(String),
+    InvalidSceneTag(String),
+    InvalidSceneFile(String),
+    SceneNotFound(String),
+    SceneAlreadyExists(String),
+    ResponseTimeout,
+    DataLoadFailed,
+    WriteFailed,
+    WrongDeviceMode(DeviceMode),
+    InvalidSceneDump(usize),
+    AmbiguousAssignment(AmbiguousAssignment),
 }
 
 impl Display for Error {
@@ -41,7 +61,39 @@ impl Display for Error {
             Error::InvalidMidiChannel(channel) =>
                 ("Invalid MIDI channel",
                 format!("Channel {} is not a valid global channel. Expected 0-16.", channel)),
+            Error::InvalidMidiValue(value) =>
+                ("Invalid MIDI value",
+                format!("{} is not a valid MIDI data value. Expected 0-127.", value)),
+            Error::InvalidGroupIndex(index) =>
+                ("Invalid group index",
+                format!("{} is not a valid group index. Expected 0-7.", index)),
             Error::ConnectionClosed => ("Connection closed", "Connection closed".to_string()),
+            Error::Io(ref err) => ("IO", err.to_string()),
+            Error::InvalidSessionFile(line) =>
+                ("Invalid session file", format!("Line {} could not be parsed.", line)),
+            Error::InvalidSceneName(ref name) =>
+                ("Invalid scene name", format!("\"{}\" cannot be used as a scene name.", name)),
+            Error::InvalidSceneTag(ref tag) =>
+                ("Invalid scene tag", format!("\"{}\" cannot be used as a scene tag.", tag)),
+            Error::InvalidSceneFile(ref name) =>
+                ("Invalid scene file", format!("Scene \"{}\" could not be parsed.", name)),
+            Error::SceneNotFound(ref name) =>
+                ("Scene not found", format!("Scene \"{}\" is not in the library.", name)),
+            Error::SceneAlreadyExists(ref name) =>
+                ("Scene already exists", format!("Scene \"{}\" is already in the library.", name)),
+            Error::ResponseTimeout =>
+                ("Response timeout", "The device did not answer in time.".to_string()),
+            Error::DataLoadFailed =>
+                ("Data load failed", "The device rejected the scene data.".to_string()),
+            Error::WriteFailed =>
+                ("Write failed", "The device could not write the scene.".to_string()),
+            Error::WrongDeviceMode(mode) =>
+                ("Wrong device mode", format!("This is not possible in {} mode.", mode)),
+            Error::InvalidSceneDump(length) =>
+                ("Invalid scene dump",
+                format!("The scene dump is {} bytes long. Expected at least 389.", length)),
+            Error::AmbiguousAssignment(ref ambiguity) =>
+                ("Ambiguous assignment", ambiguity.to_string()),
         };
 
         write!(f, "{} error: {}", error_type, error)
@@ -51,16 +103,31 @@ impl Display for Error {
 impl std::error::Error for Error {
     fn description(&self) -> &str {
         match self {
-            Error::MidirInit(ref err) => err.description(),
-            Error::MidirPortInfo(ref err) => err.description(),
-            Error::MidirSend(ref err) => err.description(),
+            Error::MidirInit(_) => "Midir initialization failed.",
+            Error::MidirPortInfo(_) => "Midir could not read port info.",
+            Error::MidirSend(_) => "Midir failed to send a message.",
             Error::MidiInputPortNotFound => "MIDI input device was not found.",
             Error::MidiOutputPortNotFound => "MIDI output device was not found.",
             Error::InvalidGlobalChannel(_) => "Invalid global MIDI channel.",
             Error::InvalidControlMode(_) => "Invalid control mode.",
             Error::InvalidLedMode(_) => "Invalid LED mode.",
             Error::InvalidMidiChannel(_) => "Invalid MIDI channel.",
-            Error::ConnectionClosed => "Connection closed."
+            Error::InvalidMidiValue(_) => "Invalid MIDI value.",
+            Error::InvalidGroupIndex(_) => "Invalid group index.",
+            Error::ConnectionClosed => "Connection closed.",
+            Error::Io(_) => "IO error.",
+            Error::InvalidSessionFile(_) => "Invalid session file.",
+            Error::InvalidSceneName(_) => "Invalid scene name.",
+            Error::InvalidSceneTag(_) => "Invalid scene tag.",
+            Error::InvalidSceneFile(_) => "Invalid scene file.",
+            Error::SceneNotFound(_) => "Scene not found.",
+            Error::SceneAlreadyExists(_) => "Scene already exists.",
+            Error::ResponseTimeout => "Response timeout.",
+            Error::DataLoadFailed => "Data load failed.",
+            Error::WriteFailed => "Write failed.",
+            Error::WrongDeviceMode(_) => "Wrong device mode.",
+            Error::InvalidSceneDump(_) => "Invalid scene dump.",
+            Error::AmbiguousAssignment(_) => "Ambiguous assignment.",
         }
     }
 
@@ -69,6 +136,7 @@ impl std::error::Error for Error {
             Error::MidirInit(err) => Some(err),
             Error::MidirPortInfo(err) => Some(err),
             Error::MidirSend(err) => Some(err),
+            Error::Io(err) => Some(err.as_ref()),
             _ => None,
         }
     }
@@ -91,3 +159,9 @@ impl From<midir::SendError> for Error {
         Error::MidirSend(err)
     }
 }
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(Arc::new(err))
+    }
+}
@@ -0,0 +1,39 @@
+use super::control::ControlId;
+use super::gesture::GestureEvent;
+use super::motion::Motion;
+use super::shift::Layer;
+use super::types::U7;
+
+/// A decoded control change, timestamped with the microseconds reported by midir.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlEvent {
+    pub timestamp: u64,
+    pub control: ControlId,
+    pub value: U7,
+    /// The strip's channel across all banks, `None` for transport buttons.
+    pub virtual_channel: Option<usize>,
+    pub layer: Layer,
+    /// How the slider or knob is moving, `None` for buttons.
+    pub motion: Option<Motion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BankEvent {
+    pub timestamp: u64,
+    pub bank: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayerEvent {
+    pub timestamp: u64,
+    pub layer: Layer,
+    pub latched: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Control(ControlEvent),
+    Gesture(GestureEvent),
+    BankChanged(BankEvent),
+    LayerChanged(LayerEvent),
+}
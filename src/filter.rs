@@ -0,0 +1,258 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::control::ControlId;
+use super::types::U7;
+
+/// Noise filtering applied to incoming values before they update `Data`.
+///
+/// The default configuration lets every value through.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct FilterConfig {
+    /// Minimum change from the last reported value before a new value is reported. The end stops
+    /// (0 and 127) are always reported.
+    pub hysteresis: u8,
+    /// Minimum number of microseconds between two reported values. The last value held back is
+    /// reported by `InputFilter::flush` once the interval has passed.
+    pub min_interval: u64,
+    /// Number of samples averaged before the value is reported. 0 and 1 disable smoothing.
+    pub smoothing: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct FilterStats {
+    pub received: u64,
+    pub suppressed: u64,
+}
+
+impl FilterStats {
+    fn record(&mut self, suppressed: bool) {
+        self.received += 1;
+        if suppressed {
+            self.suppressed += 1;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct FilterState {
+    samples: VecDeque<u8>,
+    last_value: Option<U7>,
+    last_timestamp: Option<u64>,
+    /// The latest value held back by the minimum interval.
+    pending: Option<U7>,
+    stats: FilterStats,
+}
+
+/// Applies `FilterConfig`s to the input path, either globally or per control.
+///
+/// The global configuration only applies to sliders and knobs, since rate limiting a button could
+/// swallow its release. Buttons can still be given a configuration of their own.
+#[derive(Debug, Default)]
+pub struct InputFilter {
+    global: FilterConfig,
+    overrides: HashMap<ControlId, FilterConfig>,
+    states: HashMap<ControlId, FilterState>,
+    stats: FilterStats,
+}
+
+impl InputFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_global_config(&self) -> FilterConfig {
+        self.global
+    }
+
+    pub fn set_global_config(&mut self, config: FilterConfig) {
+        self.global = config;
+    }
+
+    pub fn get_config(&self, control: ControlId) -> FilterConfig {
+        get_config(self.global, &self.overrides, control)
+    }
+
+    pub fn set_config(&mut self, control: ControlId, config: FilterConfig) {
+        self.overrides.insert(control, config);
+    }
+
+    /// Removes a per control configuration, returning the control to the global configuration.
+    pub fn clear_config(&mut self, control: ControlId) {
+        self.overrides.remove(&control);
+    }
+
+    /// Message counts across all controls.
+    pub fn get_stats(&self) -> FilterStats {
+        self.stats
+    }
+
+    pub fn get_control_stats(&self, control: ControlId) -> FilterStats {
+        self.states.get(&control).map(|state| state.stats).unwrap_or_default()
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = FilterStats::default();
+        for state in self.states.values_mut() {
+            state.stats = FilterStats::default();
+        }
+    }
+
+    /// Feeds a value received from the device, returning the value to report, if any.
+    pub fn process(&mut self, timestamp: u64, control: ControlId, value: U7) -> Option<U7> {
+        let config = self.get_config(control);
+        let state = self.states.entry(control).or_default();
+
+        let result = filter(&config, state, timestamp, value);
+        state.stats.record(result.is_none());
+        self.stats.record(result.is_none());
+        result
+    }
+
+    /// Reports the values held back by the minimum interval whose interval has passed by
+    /// `timestamp`, as `(timestamp, control, value)` with the time the interval ended.
+    pub fn flush(&mut self, timestamp: u64) -> Vec<(u64, ControlId, U7)> {
+        let mut flushed = Vec::new();
+        for (&control, state) in self.states.iter_mut() {
+            let config = get_config(self.global, &self.overrides, control);
+            let (value, last_timestamp) = match (state.pending, state.last_timestamp) {
+                (Some(value), Some(last_timestamp)) => (value, last_timestamp),
+                _ => continue,
+            };
+            let flush_timestamp = last_timestamp.saturating_add(config.min_interval);
+            if flush_timestamp <= timestamp {
+                state.pending = None;
+                state.last_value = Some(value);
+                state.last_timestamp = Some(flush_timestamp);
+                flushed.push((flush_timestamp, control, value));
+            }
+        }
+        flushed.sort_by_key(|&(timestamp, control, _)| (timestamp, control.get_index()));
+        flushed
+    }
+}
+
+fn get_config(global: FilterConfig, overrides: &HashMap<ControlId, FilterConfig>,
+    control: ControlId) -> FilterConfig {
+    match overrides.get(&control) {
+        Some(&config) => config,
+        None => match control {
+            ControlId::Slider(_) | ControlId::Knob(_) => global,
+            _ => FilterConfig::default(),
+        },
+    }
+}
+
+fn filter(config: &FilterConfig, state: &mut FilterState, timestamp: u64, value: U7)
+-> Option<U7> {
+    let value = match config.smoothing {
+        0 | 1 => {
+            state.samples.clear();
+            value
+        },
+        window => {
+            state.samples.push_back(value.get());
+            while state.samples.len() > window {
+                state.samples.pop_front();
+            }
+            let sum: u32 = state.samples.iter().map(|&n| n as u32).sum();
+            let count = state.samples.len() as u32;
+            U7::from_masked(((sum + count / 2) / count) as u8)
+        },
+    };
+
+    if let Some(last_value) = state.last_value {
+        let is_end_stop = (value == U7::MIN || value == U7::MAX) && value != last_value;
+        let difference = (value.get() as i16 - last_value.get() as i16).unsigned_abs();
+        if config.hysteresis > 0 && !is_end_stop && difference < config.hysteresis as u16 {
+            // the control settled close enough to the reported value
+            state.pending = None;
+            return None;
+        }
+    }
+
+    if let Some(last_timestamp) = state.last_timestamp {
+        if timestamp.saturating_sub(last_timestamp) < config.min_interval {
+            state.pending = Some(value);
+            return None;
+        }
+    }
+
+    state.pending = None;
+    state.last_value = Some(value);
+    state.last_timestamp = Some(timestamp);
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(input_filter: &mut InputFilter, timestamp: u64, control: ControlId, value: u8)
+    -> Option<u8> {
+        input_filter.process(timestamp, control, U7::from_const(value)).map(U7::get)
+    }
+
+    fn input_filter(config: FilterConfig) -> InputFilter {
+        let mut input_filter = InputFilter::new();
+        input_filter.set_global_config(config);
+        input_filter
+    }
+
+    #[test]
+    fn hysteresis() {
+        let mut input_filter = input_filter(FilterConfig { hysteresis: 3, ..Default::default() });
+        let slider = ControlId::Slider(0);
+        assert_eq!(process(&mut input_filter, 0, slider, 64), Some(64));
+        assert_eq!(process(&mut input_filter, 1, slider, 66), None);
+        assert_eq!(process(&mut input_filter, 2, slider, 62), None);
+        assert_eq!(process(&mut input_filter, 3, slider, 67), Some(67));
+        assert_eq!(input_filter.get_stats(), FilterStats { received: 4, suppressed: 2 });
+    }
+
+    #[test]
+    fn hysteresis_reports_end_stops() {
+        let mut input_filter = input_filter(FilterConfig { hysteresis: 5, ..Default::default() });
+        let knob = ControlId::Knob(0);
+        assert_eq!(process(&mut input_filter, 0, knob, 125), Some(125));
+        assert_eq!(process(&mut input_filter, 1, knob, 127), Some(127));
+        assert_eq!(process(&mut input_filter, 2, knob, 127), None);
+        assert_eq!(process(&mut input_filter, 3, knob, 2), Some(2));
+        assert_eq!(process(&mut input_filter, 4, knob, 0), Some(0));
+    }
+
+    #[test]
+    fn smoothing() {
+        let mut input_filter = input_filter(FilterConfig { smoothing: 4, ..Default::default() });
+        let slider = ControlId::Slider(0);
+        assert_eq!(process(&mut input_filter, 0, slider, 0), Some(0));
+        assert_eq!(process(&mut input_filter, 1, slider, 100), Some(50));
+        assert_eq!(process(&mut input_filter, 2, slider, 100), Some(67));
+        assert_eq!(process(&mut input_filter, 3, slider, 100), Some(75));
+        assert_eq!(process(&mut input_filter, 4, slider, 100), Some(100));
+    }
+
+    #[test]
+    fn rate_limiting_flushes_the_last_value() {
+        let config = FilterConfig { min_interval: 10_000, ..Default::default() };
+        let mut input_filter = input_filter(config);
+        let slider = ControlId::Slider(0);
+        assert_eq!(process(&mut input_filter, 0, slider, 10), Some(10));
+        assert_eq!(process(&mut input_filter, 1_000, slider, 20), None);
+        assert_eq!(process(&mut input_filter, 2_000, slider, 30), None);
+        assert_eq!(input_filter.flush(9_999), Vec::new());
+        assert_eq!(input_filter.flush(12_000), vec![(10_000, slider, U7::from_const(30))]);
+        assert_eq!(input_filter.flush(30_000), Vec::new());
+        assert_eq!(process(&mut input_filter, 15_000, slider, 40), None);
+        assert_eq!(process(&mut input_filter, 20_000, slider, 50), Some(50));
+        assert_eq!(input_filter.flush(30_000), Vec::new());
+    }
+
+    #[test]
+    fn buttons_ignore_the_global_config() {
+        let config = FilterConfig { min_interval: 10_000, ..Default::default() };
+        let mut input_filter = input_filter(config);
+        let solo = ControlId::Solo(0);
+        assert_eq!(process(&mut input_filter, 0, solo, 127), Some(127));
+        assert_eq!(process(&mut input_filter, 1, solo, 0), Some(0));
+    }
+}
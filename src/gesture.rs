@@ -0,0 +1,232 @@
+use std::collections::{HashMap, HashSet};
+
+use super::control::ControlId;
+use super::motion::Direction;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Gesture {
+    /// A button was pressed and released before the long press threshold.
+    Press(ControlId),
+    /// A button was pressed again within the double tap window of a previous press.
+    DoubleTap(ControlId),
+    /// A button was held for at least the long press threshold.
+    LongPress(ControlId),
+    /// Every button of a registered chord is held, in the order the chord was registered.
+    Chord(Vec<ControlId>),
+    /// A slider or knob was thrown, reaching a speed in value steps per second of at least the
+    /// flick speed.
+    Flick { control: ControlId, direction: Direction, speed: u32 },
+}
+
+/// A recognized gesture, timestamped with the microseconds reported by midir.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GestureEvent {
+    pub timestamp: u64,
+    pub gesture: Gesture,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GestureConfig {
+    /// Maximum number of microseconds between two presses for them to count as a double tap.
+    pub double_tap_window: u64,
+    /// Minimum number of microseconds a button is held for it to count as a long press.
+    pub long_press_threshold: u64,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        GestureConfig {
+            double_tap_window: 300_000,
+            long_press_threshold: 500_000,
+        }
+    }
+}
+
+/// Recognizes gestures from button presses and releases.
+///
+/// Presses are reported when the button is released, so a double tap reports a press followed by
+/// the double tap. Long presses are reported by `poll` as soon as the threshold passes, or on
+/// release when `poll` was not called in time. Buttons taking part in a long press, double tap or
+/// chord report nothing when released.
+#[derive(Debug, Default)]
+pub struct GestureRecognizer {
+    pub config: GestureConfig,
+    chords: Vec<Vec<ControlId>>,
+    held: HashMap<ControlId, u64>,
+    consumed: HashSet<ControlId>,
+    last_tap: HashMap<ControlId, u64>,
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> Self {
+        GestureRecognizer {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn add_chord(&mut self, buttons: &[ControlId]) {
+        if buttons.len() > 1 && !self.chords.iter().any(|chord| chord == buttons) {
+            self.chords.push(buttons.to_vec());
+        }
+    }
+
+    pub fn remove_chord(&mut self, buttons: &[ControlId]) {
+        self.chords.retain(|chord| chord != buttons);
+    }
+
+    pub fn get_chords(&self) -> &[Vec<ControlId>] {
+        &self.chords
+    }
+
+    pub fn is_held(&self, control: ControlId) -> bool {
+        self.held.contains_key(&control)
+    }
+
+    /// Feeds a button transition, returning the gestures it completes.
+    pub fn process(&mut self, timestamp: u64, control: ControlId, pressed: bool) -> Vec<GestureEvent> {
+        let mut gestures = Vec::new();
+        match pressed {
+            true => self.press(timestamp, control, &mut gestures),
+            false => self.release(timestamp, control, &mut gestures),
+        }
+        gestures
+    }
+
+    /// Reports the long presses of buttons held for the threshold by `timestamp`, timestamped with
+    /// the time the threshold passed.
+    pub fn poll(&mut self, timestamp: u64) -> Vec<GestureEvent> {
+        let threshold = self.config.long_press_threshold;
+        let mut long_presses: Vec<(u64, ControlId)> = self.held.iter()
+            .filter(|&(control, _)| !self.consumed.contains(control))
+            .map(|(&control, &pressed_at)| (pressed_at.saturating_add(threshold), control))
+            .filter(|&(long_press_at, _)| long_press_at <= timestamp)
+            .collect();
+        long_presses.sort_by_key(|&(long_press_at, control)| (long_press_at, control.get_index()));
+
+        long_presses.into_iter().map(|(long_press_at, control)| {
+            self.consumed.insert(control);
+            GestureEvent { timestamp: long_press_at, gesture: Gesture::LongPress(control) }
+        }).collect()
+    }
+
+    fn press(&mut self, timestamp: u64, control: ControlId, gestures: &mut Vec<GestureEvent>) {
+        if self.held.insert(control, timestamp).is_some() {
+            return;
+        }
+
+        let held = &self.held;
+        let consumed = &self.consumed;
+        let completed_chord = self.chords.iter()
+            .filter(|chord| chord.contains(&control))
+            .find(|chord| chord.iter().all(|button| {
+                held.contains_key(button) && !consumed.contains(button)
+            }))
+            .cloned();
+
+        if let Some(chord) = completed_chord {
+            self.consumed.extend(chord.iter().cloned());
+            gestures.push(GestureEvent { timestamp, gesture: Gesture::Chord(chord) });
+            return;
+        }
+
+        if let Some(last_tap) = self.last_tap.remove(&control) {
+            if timestamp.saturating_sub(last_tap) <= self.config.double_tap_window {
+                self.consumed.insert(control);
+                gestures.push(GestureEvent { timestamp, gesture: Gesture::DoubleTap(control) });
+            }
+        }
+    }
+
+    fn release(&mut self, timestamp: u64, control: ControlId, gestures: &mut Vec<GestureEvent>) {
+        let pressed_at = match self.held.remove(&control) {
+            Some(pressed_at) => pressed_at,
+            None => return,
+        };
+
+        if self.consumed.remove(&control) {
+            return;
+        }
+
+        let gesture = match timestamp.saturating_sub(pressed_at) >= self.config.long_press_threshold {
+            true => Gesture::LongPress(control),
+            false => {
+                self.last_tap.insert(control, pressed_at);
+                Gesture::Press(control)
+            },
+        };
+        gestures.push(GestureEvent { timestamp, gesture });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOLO: ControlId = ControlId::Solo(0);
+    const MUTE: ControlId = ControlId::Mute(0);
+
+    fn gestures(events: Vec<GestureEvent>) -> Vec<Gesture> {
+        events.into_iter().map(|event| event.gesture).collect()
+    }
+
+    #[test]
+    fn press() {
+        let mut recognizer = GestureRecognizer::default();
+        assert_eq!(recognizer.process(0, SOLO, true), Vec::new());
+        assert_eq!(recognizer.poll(100_000), Vec::new());
+        assert_eq!(gestures(recognizer.process(100_000, SOLO, false)), vec![Gesture::Press(SOLO)]);
+    }
+
+    #[test]
+    fn double_tap() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.process(0, SOLO, true);
+        recognizer.process(50_000, SOLO, false);
+        assert_eq!(gestures(recognizer.process(200_000, SOLO, true)),
+            vec![Gesture::DoubleTap(SOLO)]);
+        assert_eq!(recognizer.process(250_000, SOLO, false), Vec::new());
+    }
+
+    #[test]
+    fn taps_outside_the_window_are_presses() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.process(0, SOLO, true);
+        recognizer.process(50_000, SOLO, false);
+        assert_eq!(recognizer.process(400_000, SOLO, true), Vec::new());
+        assert_eq!(gestures(recognizer.process(450_000, SOLO, false)), vec![Gesture::Press(SOLO)]);
+    }
+
+    #[test]
+    fn long_press_while_held() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.process(0, SOLO, true);
+        assert_eq!(recognizer.poll(499_999), Vec::new());
+        assert_eq!(recognizer.poll(600_000), vec![GestureEvent {
+            timestamp: 500_000,
+            gesture: Gesture::LongPress(SOLO),
+        }]);
+        assert_eq!(recognizer.poll(700_000), Vec::new());
+        assert_eq!(recognizer.process(800_000, SOLO, false), Vec::new());
+    }
+
+    #[test]
+    fn long_press_on_release_without_poll() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.process(0, SOLO, true);
+        assert_eq!(gestures(recognizer.process(600_000, SOLO, false)),
+            vec![Gesture::LongPress(SOLO)]);
+    }
+
+    #[test]
+    fn chord_in_registration_order() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.add_chord(&[SOLO, MUTE]);
+        assert_eq!(recognizer.process(0, MUTE, true), Vec::new());
+        assert_eq!(gestures(recognizer.process(10_000, SOLO, true)),
+            vec![Gesture::Chord(vec![SOLO, MUTE])]);
+        assert_eq!(recognizer.poll(1_000_000), Vec::new());
+        assert_eq!(recognizer.process(1_000_000, SOLO, false), Vec::new());
+        assert_eq!(recognizer.process(1_000_000, MUTE, false), Vec::new());
+    }
+}
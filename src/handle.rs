@@ -0,0 +1,127 @@
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+
+use super::connection::OutputSender;
+use super::control::ControlId;
+use super::data::Snapshot;
+use super::event::{ControlEvent, Event};
+use super::parameters::Parameters;
+use super::subscription::{Subscription, Subscriptions};
+use super::types::U7;
+use super::{KorgNanokontrol2, Result};
+
+/// A cloneable handle sharing one device between threads.
+///
+/// One thread drives the device with `poll_events`, which publishes a snapshot of the control
+/// values after every call. Snapshots are read without waiting for the device, while output
+/// methods lock the device and can be called from any thread. Callbacks subscribed through the
+/// handle run after `poll_events` released the device, so they may use the handle themselves.
+#[derive(Clone)]
+pub struct DeviceHandle {
+    device: Arc<Mutex<KorgNanokontrol2>>,
+    snapshot: Arc<RwLock<Snapshot>>,
+    subscriptions: Subscriptions,
+}
+
+impl DeviceHandle {
+    pub fn new(device: KorgNanokontrol2) -> Self {
+        let snapshot = device.snapshot();
+        DeviceHandle {
+            device: Arc::new(Mutex::new(device)),
+            snapshot: Arc::new(RwLock::new(snapshot)),
+            subscriptions: Subscriptions::default(),
+        }
+    }
+
+    /// Locks the device for anything the handle does not cover. The snapshot is only updated by
+    /// the handle's own methods. Callbacks subscribed on the locked device run with the lock held
+    /// and must not use the handle.
+    pub fn lock(&self) -> MutexGuard<'_, KorgNanokontrol2> {
+        self.device.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Applies all input received since the last call and publishes the resulting snapshot, then
+    /// calls the callbacks subscribed through the handle.
+    pub fn poll_events(&self) -> Vec<Event> {
+        let events = self.with_device(|device| device.poll_events());
+        for event in &events {
+            if let Event::Control(event) = event {
+                self.subscriptions.dispatch(event);
+            }
+        }
+        events
+    }
+
+    /// The control values as of the last `poll_events` call or output method.
+    pub fn snapshot(&self) -> Snapshot {
+        self.snapshot.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Subscribes to the events of `control`, called from the thread driving `poll_events` once
+    /// the device is unlocked.
+    pub fn on<F>(&self, control: ControlId, callback: F) -> Subscription where
+        F: FnMut(&ControlEvent) + Send + 'static {
+        self.subscriptions.on(control, callback)
+    }
+
+    /// Subscribes to every button event, called from the thread driving `poll_events` once the
+    /// device is unlocked.
+    pub fn on_any_button<F>(&self, callback: F) -> Subscription where
+        F: FnMut(&ControlEvent) + Send + 'static {
+        self.subscriptions.on_any_button(callback)
+    }
+
+    pub fn set_button_led(&self, control: ControlId, on: bool) -> Result<()> {
+        self.with_device(|device| device.set_button_led(control, on))
+    }
+
+    pub fn set_slider_value_raw(&self, group_index: usize, value: U7) {
+        self.with_device(|device| device.set_slider_value_raw(group_index, value))
+    }
+
+    pub fn set_knob_value_raw(&self, group_index: usize, value: U7) {
+        self.with_device(|device| device.set_knob_value_raw(group_index, value))
+    }
+
+    pub fn get_output_sender(&self) -> OutputSender {
+        self.lock().get_output_sender()
+    }
+
+    pub fn select_bank(&self, bank: usize) -> Result<bool> {
+        self.with_device(|device| device.select_bank(bank))
+    }
+
+    pub fn write_scene(&self, parameters: Parameters) -> Result<()> {
+        self.with_device(|device| device.write_scene(parameters))
+    }
+
+    fn with_device<T, F>(&self, f: F) -> T where F: FnOnce(&mut KorgNanokontrol2) -> T {
+        let mut device = self.lock();
+        let result = f(&mut device);
+        let snapshot = device.snapshot();
+        *self.snapshot.write().unwrap_or_else(PoisonError::into_inner) = snapshot;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use super::*;
+
+    #[test]
+    fn callbacks_can_use_the_handle() {
+        let handle = DeviceHandle::new(KorgNanokontrol2::new());
+        let (sender, receiver) = channel();
+        let callback_handle = handle.clone();
+        handle.on(ControlId::Solo(0), move |event| {
+            // would deadlock if the device were still locked
+            callback_handle.set_button_led(event.control, true).ok();
+            sender.send(event.value).ok();
+        }).detach();
+
+        handle.lock().receive(0, &[0xB0, 0x20, 0x7F]);
+        handle.poll_events();
+        assert_eq!(receiver.try_recv().ok(), Some(U7::MAX));
+    }
+}
@@ -1,105 +1,902 @@
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod bank;
+pub mod builder;
 pub mod connection;
+pub mod control;
+pub mod curve;
 pub mod data;
+pub mod device;
 pub mod enums;
 pub mod error;
+pub mod event;
+pub mod filter;
+pub mod gesture;
+pub mod handle;
+pub mod library;
+pub mod lookup;
+pub mod motion;
 pub mod parameters;
+mod request;
+pub mod session;
+pub mod shift;
+pub mod smf;
+pub mod subscription;
+pub mod takeover;
+pub mod types;
 
-use connection::Connection;
-use data::Data;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use bank::Banks;
+use connection::{Connection, IoType, MessageHandler, OutputSender};
+use curve::ValueMapping;
+use data::{Data, Snapshot};
+use device::{DeviceInfo, DeviceMode, DeviceResponse, NativeModeGuard};
 use enums::*;
 pub use error::{Result, Error};
+use control::ControlId;
+use event::{BankEvent, ControlEvent, Event, LayerEvent};
+use filter::InputFilter;
+use gesture::GestureRecognizer;
+use lookup::ControlLookup;
+use motion::MotionTracker;
 use parameters::*;
+use request::{PendingRequests, Request};
+use session::Session;
+use shift::{Layer, ShiftLayer};
+use subscription::{Subscription, Subscriptions};
+use takeover::{GroupTakeover, Takeover, TakeoverMode};
+use types::{CcNumber, Channel, GlobalChannel, U7};
+
+struct Input {
+    timestamp: u64,
+    message: Vec<u8>,
+}
+
+type ChannelMessageCallback = Box<dyn FnMut(u64, &[u8]) + Send>;
+type ResponseListener = Box<dyn FnMut(u64, &DeviceResponse) + Send>;
+
+/// A request waiting for the device to answer. Responses go to the oldest request accepting them.
+enum ControlInput {
+    ControlChange(Channel, CcNumber, U7),
+    Note(Channel, U7, bool),
+}
+
+#[derive(Default)]
+struct DecodedMessage {
+    control_input: Option<ControlInput>,
+    is_channel_message: bool,
+    scene_dump: Option<Vec<u8>>,
+    device_info: Option<DeviceInfo>,
+    responses: Vec<DeviceResponse>,
+    reply_channel: Option<GlobalChannel>,
+}
+
+impl MessageHandler for DecodedMessage {
+    fn control_change(&mut self, _timestamp: u64, midi_channel: Channel, control_change: CcNumber,
+        value: U7) {
+        self.control_input = Some(ControlInput::ControlChange(midi_channel, control_change, value));
+    }
+
+    fn note_on(&mut self, _timestamp: u64, midi_channel: Channel, note_number: U7, _velocity: U7) {
+        self.control_input = Some(ControlInput::Note(midi_channel, note_number, true));
+    }
+
+    fn note_off(&mut self, _timestamp: u64, midi_channel: Channel, note_number: U7, _velocity: U7) {
+        self.control_input = Some(ControlInput::Note(midi_channel, note_number, false));
+    }
+
+    fn channel_message(&mut self, _timestamp: u64, _message: &[u8]) {
+        self.is_channel_message = true;
+    }
+
+    fn system_exclusive(&mut self, _timestamp: u64, global_channel: GlobalChannel,
+        command_value: u8, _function_id: u8, data: &[u8]) {
+        self.reply_channel = Some(global_channel);
+        if command_value == 0x7F {
+            self.scene_dump = Some(data.to_vec());
+        }
+    }
+
+    fn device_response(&mut self, _timestamp: u64, _global_channel: GlobalChannel,
+        response: DeviceResponse) {
+        self.responses.push(response);
+    }
+
+    fn device_info(&mut self, _timestamp: u64, device_info: DeviceInfo) {
+        self.reply_channel = Some(device_info.global_channel);
+        self.device_info = Some(device_info);
+    }
+}
 
 pub struct KorgNanokontrol2 {
     connection: Connection,
     parameters: Parameters,
+    lookup: ControlLookup,
     data: Data,
+    timestamps: [Option<u64>; 51],
+    slider_mappings: [ValueMapping; 8],
+    knob_mappings: [ValueMapping; 8],
+    takeover: [GroupTakeover; 8],
+    filter: InputFilter,
+    gestures: GestureRecognizer,
+    motion: MotionTracker,
+    subscriptions: Subscriptions,
+    banks: Banks,
+    shift: ShiftLayer,
+    recording: Option<Session>,
+    device_info: Option<DeviceInfo>,
+    mode: Option<DeviceMode>,
+    reply_channel: Option<GlobalChannel>,
+    channel_message_callback: Option<ChannelMessageCallback>,
+    response_listener: Option<ResponseListener>,
+    pending_requests: PendingRequests<DeviceResponse, Sender<DeviceResponse>>,
+    response_timeout: Duration,
+    pending_events: Vec<Event>,
+    /// The timestamp of the last input and when it was handled, to tell the time in midir's
+    /// timestamps.
+    clock: Option<(u64, Instant)>,
+    input_sender: Sender<Input>,
+    input_receiver: Receiver<Input>,
+}
+
+impl Default for KorgNanokontrol2 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl KorgNanokontrol2 {
+    pub fn new() -> Self {
+        let (input_sender, input_receiver) = channel();
+        let parameters = Parameters::factory_default();
+        KorgNanokontrol2 {
+            connection: Connection::new(),
+            lookup: parameters.build_lookup(),
+            parameters,
+            data: Data::default(),
+            timestamps: [None; 51],
+            slider_mappings: Default::default(),
+            knob_mappings: Default::default(),
+            takeover: Default::default(),
+            filter: InputFilter::new(),
+            gestures: GestureRecognizer::default(),
+            motion: MotionTracker::default(),
+            subscriptions: Subscriptions::default(),
+            banks: Banks::default(),
+            shift: ShiftLayer::default(),
+            recording: None,
+            device_info: None,
+            mode: None,
+            reply_channel: None,
+            channel_message_callback: None,
+            response_listener: None,
+            pending_requests: PendingRequests::default(),
+            response_timeout: Duration::from_secs(2),
+            pending_events: Vec::new(),
+            clock: None,
+            input_sender,
+            input_receiver,
+        }
+    }
+
+    /// Opens the connection and finds the device's global channel, then requests its mode and
+    /// current scene. The connection is closed again if any of this fails.
     pub fn connect(&mut self) -> Result<()> {
-        self.connection.open(
-            |_, _, _, _| (),
-            |timestamp, midi_channel, command_value, function_id, data| {
-                match command_value {
-                    0x40 => (),
-                    0x5F => (),
-                    0x7F => (),
-                    _ => (),
+        let input_sender = self.input_sender.clone();
+        self.connection.open_raw(move |timestamp, message| {
+            input_sender.send(Input { timestamp, message: message.to_vec() }).ok();
+        })?;
+
+        let result = self.discover_global_channel()
+            .and_then(|_| self.connection.mode_request())
+            .and_then(|_| self.connection.current_scene_data_dump_request());
+        if result.is_err() {
+            self.disconnect();
+        }
+        result
+    }
+
+    /// Finds the global channel the device answers on and uses it for all further requests. The
+    /// identity request is tried first, then scene dump requests on every channel.
+    pub fn discover_global_channel(&mut self) -> Result<GlobalChannel> {
+        self.reply_channel = None;
+        self.connection.device_inquiry_request()?;
+        let mut global_channel = self.handle_input_until(|device| device.reply_channel);
+
+        if global_channel.is_none() {
+            self.connection.probe_global_channels()?;
+            global_channel = self.handle_input_until(|device| device.reply_channel);
+        }
+
+        let global_channel = global_channel.ok_or(Error::ResponseTimeout)?;
+        self.connection.set_global_channel(global_channel);
+        Ok(global_channel)
+    }
+
+    /// Asks the device to identify itself. The reply is picked up by `poll_events` and available
+    /// from `get_device_info`.
+    pub fn request_device_info(&mut self) -> Result<()> {
+        self.connection.device_inquiry_request()
+    }
+
+    /// The identity last reported by the device.
+    pub fn get_device_info(&self) -> Option<&DeviceInfo> {
+        self.device_info.as_ref()
+    }
+
+    pub fn disconnect(&mut self) {
+        self.connection.close();
+        self.mode = None;
+    }
+
+    /// Asks the device whether it is in normal or native mode, waiting for the answer.
+    pub fn query_mode(&mut self) -> Result<DeviceMode> {
+        match self.send_request(Request::Mode)? {
+            DeviceResponse::ModeData(mode) => Ok(mode),
+            _ => unreachable!("only mode data is accepted"),
+        }
+    }
+
+    /// Switches the device into native mode, waiting for the device to acknowledge it.
+    pub fn enter_native_mode(&mut self) -> Result<()> {
+        self.set_native_mode(IoType::In)
+    }
+
+    /// Switches the device back to normal mode, waiting for the device to acknowledge it.
+    pub fn exit_native_mode(&mut self) -> Result<()> {
+        self.set_native_mode(IoType::Out)
+    }
+
+    /// Switches the device into native mode until the returned guard is dropped, including when
+    /// unwinding from a panic.
+    pub fn enter_native_mode_guard(&mut self) -> Result<NativeModeGuard<'_>> {
+        self.enter_native_mode()?;
+        Ok(NativeModeGuard::new(self))
+    }
+
+    fn set_native_mode(&mut self, io_type: IoType) -> Result<()> {
+        self.send_request(Request::NativeMode(io_type))?;
+        Ok(())
+    }
+
+    /// The mode last reported by the device, `None` until it answered a mode request.
+    pub fn get_mode(&self) -> Option<DeviceMode> {
+        self.mode
+    }
+
+    /// Fails with `Error::WrongDeviceMode` when the device is known to be in another mode.
+    pub fn check_mode(&self, mode: DeviceMode) -> Result<()> {
+        request::check_mode(self.mode, mode)
+    }
+
+    /// Applies all input received since the last call, returning the resulting events.
+    pub fn poll_events(&mut self) -> Vec<Event> {
+        let inputs: Vec<Input> = self.input_receiver.try_iter().collect();
+
+        let mut events = std::mem::take(&mut self.pending_events);
+        for input in inputs {
+            self.handle_input(input, &mut events);
+        }
+        if let Some(timestamp) = self.get_current_timestamp() {
+            self.handle_timeouts(timestamp, &mut events);
+        }
+        events
+    }
+
+    /// The current time in the microseconds of input timestamps, estimated from the last input.
+    fn get_current_timestamp(&self) -> Option<u64> {
+        self.clock.map(|(timestamp, handled_at)| {
+            timestamp + handled_at.elapsed().as_micros() as u64
+        })
+    }
+
+    /// Reports what became due by `timestamp` without new input: filtered values held back by the
+    /// minimum interval and long presses.
+    fn handle_timeouts(&mut self, timestamp: u64, events: &mut Vec<Event>) {
+        for (timestamp, control, value) in self.filter.flush(timestamp) {
+            self.apply_control_value(timestamp, control, value, events);
+        }
+        events.extend(self.gestures.poll(timestamp).into_iter().map(Event::Gesture));
+    }
+
+    /// Sends a scene to the device and writes it to the device's memory, waiting for the device to
+    /// confirm both steps.
+    pub fn write_scene(&mut self, parameters: Parameters) -> Result<()> {
+        self.check_mode(DeviceMode::Normal)?;
+
+        let scene_dump = parameters.create_scene_dump();
+        self.send_request(Request::DataLoad(&scene_dump))?;
+        self.set_parameters(parameters);
+        self.send_request(Request::Write)?;
+        self.connection.set_global_channel(self.parameters.global_channel);
+        Ok(())
+    }
+
+    /// Sets a callback receiving every packet communication reply from `poll_events`, including
+    /// the ones answering a request and the ones with an unknown function ID.
+    pub fn set_response_listener<F>(&mut self, listener: F) where
+        F: FnMut(u64, &DeviceResponse) + Send + 'static {
+        self.response_listener = Some(Box::new(listener));
+    }
+
+    pub fn clear_response_listener(&mut self) {
+        self.response_listener = None;
+    }
+
+    pub fn get_response_timeout(&self) -> Duration {
+        self.response_timeout
+    }
+
+    /// Sets how long requests wait for the device to answer before failing with
+    /// `Error::ResponseTimeout`.
+    pub fn set_response_timeout(&mut self, timeout: Duration) {
+        self.response_timeout = timeout;
+    }
+
+    /// Sends a request and handles input until the response answering it arrives.
+    fn send_request(&mut self, request: Request) -> Result<DeviceResponse> {
+        let (sender, receiver) = channel();
+        let id = self.pending_requests.register(request.get_acceptor(), sender);
+        if let Err(error) = request.send(&mut self.connection) {
+            self.pending_requests.remove(id);
+            return Err(error);
+        }
+
+        match self.handle_input_until(|_| receiver.try_recv().ok()) {
+            Some(response) => request.check(response),
+            None => {
+                self.pending_requests.remove(id);
+                Err(Error::ResponseTimeout)
+            },
+        }
+    }
+
+    /// Handles input until `f` returns a value or the response timeout expires. Events produced
+    /// in the meantime are returned by the next `poll_events` call.
+    fn handle_input_until<T, F>(&mut self, mut f: F) -> Option<T> where
+        F: FnMut(&Self) -> Option<T> {
+
+        let deadline = Instant::now() + self.response_timeout;
+        loop {
+            if let Some(value) = f(self) {
+                return Some(value);
+            }
+            let input = deadline.checked_duration_since(Instant::now())
+                .and_then(|timeout| self.input_receiver.recv_timeout(timeout).ok())?;
+            let mut events = std::mem::take(&mut self.pending_events);
+            self.handle_input(input, &mut events);
+            self.pending_events = events;
+        }
+    }
+
+    fn deliver_response(&mut self, timestamp: u64, response: DeviceResponse) {
+        if let Some(mode) = response.get_mode() {
+            self.mode = Some(mode);
+        }
+        if let Some(listener) = &mut self.response_listener {
+            listener(timestamp, &response);
+        }
+
+        if let Some(sender) = self.pending_requests.take_sender(&response) {
+            sender.send(response).ok();
+        }
+    }
+
+    fn handle_input(&mut self, input: Input, events: &mut Vec<Event>) {
+        self.clock = Some((input.timestamp, Instant::now()));
+        self.handle_timeouts(input.timestamp, events);
+
+        let first_event = events.len();
+        self.handle_message(input.timestamp, &input.message, events);
+        if let Some(session) = &mut self.recording {
+            session.push(input.timestamp, &input.message, &events[first_event..]);
+        }
+    }
+
+    /// Starts capturing every message received from the device, along with the current scene.
+    pub fn start_recording(&mut self) {
+        let scene = self.parameters.create_scene_dump().to_vec();
+        self.recording = Some(Session::new(Some(scene)));
+    }
+
+    pub fn stop_recording(&mut self) -> Option<Session> {
+        self.recording.take()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Feeds a recorded session back into the event pipeline with its original timing, switching
+    /// to the scene it was recorded with. The messages are picked up by `poll_events` as if they
+    /// came from the device.
+    pub fn replay(&mut self, session: Session) -> Result<JoinHandle<Result<()>>> {
+        if let Some(parameters) = session.get_parameters() {
+            self.set_parameters(parameters?);
+        }
+
+        let input_sender = self.input_sender.clone();
+        Ok(thread::spawn(move || {
+            session.play(|entry| {
+                input_sender.send(Input { timestamp: entry.timestamp, message: entry.message.clone() })
+                    .map_err(|_| Error::ConnectionClosed)
+            })
+        }))
+    }
+
+    fn handle_message(&mut self, timestamp: u64, message: &[u8], events: &mut Vec<Event>) {
+        let mut decoded = DecodedMessage::default();
+        connection::decode_message(timestamp, message, &mut decoded);
+
+        if let Some(control_input) = decoded.control_input {
+            let (control, value) = match control_input {
+                ControlInput::ControlChange(midi_channel, control_change, value) =>
+                    (self.lookup.find_control_change(midi_channel, control_change), value),
+                ControlInput::Note(midi_channel, note_number, on) => {
+                    let control = self.lookup.find_note(midi_channel, note_number);
+                    let value = control
+                        .and_then(|control| self.parameters.get_button_value(control, on))
+                        .unwrap_or(U7::MIN);
+                    (control, value)
+                },
+            };
+            if let Some(control) = control {
+                self.handle_control_value(timestamp, control, value, events);
+            }
+        }
+        if decoded.is_channel_message {
+            if let Some(callback) = &mut self.channel_message_callback {
+                callback(timestamp, message);
+            }
+        }
+        for response in decoded.responses {
+            self.deliver_response(timestamp, response);
+        }
+        if decoded.reply_channel.is_some() {
+            self.reply_channel = decoded.reply_channel;
+        }
+        if decoded.device_info.is_some() {
+            self.device_info = decoded.device_info;
+        }
+        if let Some(scene_dump) = decoded.scene_dump {
+            if let Ok(parameters) = Parameters::parse_scene_dump(&scene_dump) {
+                self.set_parameters(parameters);
+            }
+        }
+    }
+
+    fn set_parameters(&mut self, parameters: Parameters) {
+        self.lookup = parameters.build_lookup();
+        self.parameters = parameters;
+    }
+
+    fn handle_control_value(&mut self, timestamp: u64, control: ControlId, value: U7,
+        events: &mut Vec<Event>) {
+        if let Some(value) = self.filter.process(timestamp, control, value) {
+            self.apply_control_value(timestamp, control, value, events);
+        }
+    }
+
+    fn apply_control_value(&mut self, timestamp: u64, control: ControlId, value: U7,
+        events: &mut Vec<Event>) {
+        if Some(control) == self.shift.get_button() {
+            let pressed = self.parameters.get_button_parameters(control)
+                .is_some_and(|button_parameters| get_button_state(value, button_parameters));
+            if let Some(layer) = self.shift.process(timestamp, pressed) {
+                self.apply_layer().ok();
+                let latched = self.shift.is_latched();
+                events.push(Event::LayerChanged(LayerEvent { timestamp, layer, latched }));
+            }
+            return;
+        }
+
+        let layer = self.shift.get_layer();
+        if self.banks.get_count() > 1 && layer == Layer::Base {
+            let bank_step = match control {
+                ControlId::Transport(TransportButton::TrackRewind) => Some(-1),
+                ControlId::Transport(TransportButton::TrackFastforward) => Some(1),
+                _ => None,
+            };
+            if let Some(bank_step) = bank_step {
+                let pressed = self.parameters.get_button_parameters(control)
+                    .is_some_and(|button_parameters| get_button_state(value, button_parameters));
+                let bank = self.banks.get_current() as isize + bank_step;
+                // a failed switch keeps the previous bank, so there is no change to report
+                if pressed && bank >= 0 && matches!(self.select_bank(bank as usize), Ok(true)) {
+                    events.push(Event::BankChanged(BankEvent { timestamp, bank: bank as usize }));
                 }
-            })?;
+                return;
+            }
+        }
+
+        // motion follows the physical control, even while takeover holds its value back
+        let (motion, flick) = match control.is_button() {
+            true => (None, None),
+            false => {
+                let (motion, flick) = self.motion.process(timestamp, control, value);
+                (Some(motion), flick)
+            },
+        };
+
+        let value = match self.get_takeover_mut(control) {
+            Some(takeover) => match takeover.process(value) {
+                Some(value) => value,
+                None => return,
+            },
+            None => value,
+        };
+
+        self.get_layer_data_mut().set_value(control, value);
+        self.timestamps[control.get_index()] = Some(timestamp);
+        let virtual_channel = control.get_group_index()
+            .map(|group_index| self.banks.get_virtual_channel(group_index));
+        let event = ControlEvent { timestamp, control, value, virtual_channel, layer, motion };
+        self.subscriptions.dispatch(&event);
+        events.push(Event::Control(event));
+        events.extend(flick.map(Event::Gesture));
+
+        if let Some(button_parameters) = self.parameters.get_button_parameters(control) {
+            let gestures = match button_parameters.behavior {
+                // toggle buttons only report each press, so treat them as a tap
+                ButtonBehavior::Toggle => {
+                    let mut gestures = self.gestures.process(timestamp, control, true);
+                    gestures.extend(self.gestures.process(timestamp, control, false));
+                    gestures
+                },
+                ButtonBehavior::Momentary => {
+                    let pressed = get_button_state(value, button_parameters);
+                    self.gestures.process(timestamp, control, pressed)
+                },
+            };
+            events.extend(gestures.into_iter().map(Event::Gesture));
+        }
+    }
+
+    fn get_takeover_mut(&mut self, control: ControlId) -> Option<&mut Takeover> {
+        match control {
+            ControlId::Slider(i) => Some(&mut self.takeover[i as usize].slider),
+            ControlId::Knob(i) => Some(&mut self.takeover[i as usize].knob),
+            _ => None,
+        }
+    }
+
+    /// Calls `callback` from `poll_events` with every event of `control`, until the returned
+    /// subscription is dropped.
+    pub fn on<F>(&self, control: ControlId, callback: F) -> Subscription where
+        F: FnMut(&ControlEvent) + Send + 'static {
+        self.subscriptions.on(control, callback)
+    }
+
+    /// Calls `callback` from `poll_events` with every button event, until the returned
+    /// subscription is dropped.
+    pub fn on_any_button<F>(&self, callback: F) -> Subscription where
+        F: FnMut(&ControlEvent) + Send + 'static {
+        self.subscriptions.on_any_button(callback)
+    }
+
+    /// The subscriptions of the device, which can be cloned to subscribe from elsewhere.
+    pub fn get_subscriptions(&self) -> &Subscriptions {
+        &self.subscriptions
+    }
+
+    /// Sets a callback receiving the channel voice messages that are neither control changes nor
+    /// notes, such as pitch bend, from `poll_events`.
+    pub fn set_channel_message_callback<F>(&mut self, callback: F) where
+        F: FnMut(u64, &[u8]) + Send + 'static {
+        self.channel_message_callback = Some(Box::new(callback));
+    }
+
+    pub fn clear_channel_message_callback(&mut self) {
+        self.channel_message_callback = None;
+    }
+
+    pub fn get_filter(&self) -> &InputFilter {
+        &self.filter
+    }
+
+    pub fn get_filter_mut(&mut self) -> &mut InputFilter {
+        &mut self.filter
+    }
+
+    /// Splits the strips into `count` banks of eight virtual channels, paged through with the
+    /// track buttons. A count of 1 disables banking and gives the track buttons back.
+    pub fn set_bank_count(&mut self, count: usize) {
+        self.select_bank(0).ok();
+        self.banks = Banks::new(count);
+    }
+
+    pub fn get_bank_count(&self) -> usize {
+        self.banks.get_count()
+    }
+
+    pub fn get_bank(&self) -> usize {
+        self.banks.get_current()
+    }
+
+    pub fn get_banks(&self) -> &Banks {
+        &self.banks
+    }
+
+    /// Switches to `bank`, restoring its values and, in external LED mode, its button LEDs.
+    /// Sliders and knobs take over the restored values according to their `TakeoverMode`.
+    ///
+    /// When the LEDs cannot be sent, the previous bank stays selected and the error is returned.
+    pub fn select_bank(&mut self, bank: usize) -> Result<bool> {
+        let previous_bank = self.banks.get_current();
+        if !self.banks.switch(bank, &mut self.data.groups) {
+            return Ok(false);
+        }
+
+        if let Err(error) = self.apply_layer() {
+            self.banks.switch(previous_bank, &mut self.data.groups);
+            self.apply_layer().ok();
+            return Err(error);
+        }
+        Ok(true)
+    }
+
+    /// Designates a button as the shift key, or disables the shift layer with `None`.
+    pub fn set_shift_button(&mut self, button: Option<ControlId>) -> Result<()> {
+        let layer = self.shift.get_layer();
+        self.shift.set_button(button);
+        match layer {
+            Layer::Shift => self.apply_layer(),
+            Layer::Base => Ok(()),
+        }
+    }
+
+    pub fn get_shift(&self) -> &ShiftLayer {
+        &self.shift
+    }
+
+    pub fn get_shift_mut(&mut self) -> &mut ShiftLayer {
+        &mut self.shift
+    }
+
+    pub fn get_layer(&self) -> Layer {
+        self.shift.get_layer()
+    }
+
+    fn get_layer_data(&self) -> &Data {
+        match self.shift.get_layer() {
+            Layer::Base => &self.data,
+            Layer::Shift => self.shift.get_data(),
+        }
+    }
+
+    fn get_layer_data_mut(&mut self) -> &mut Data {
+        match self.shift.get_layer() {
+            Layer::Base => &mut self.data,
+            Layer::Shift => self.shift.get_data_mut(),
+        }
+    }
+
+    /// Makes the sliders and knobs take over the values of the active layer and, in external LED
+    /// mode, shows its button LEDs.
+    fn apply_layer(&mut self) -> Result<()> {
+        let data = self.get_layer_data().clone();
+
+        for (i, group) in data.groups.iter().enumerate() {
+            self.takeover[i].slider.set_target(group.slider_value);
+            self.takeover[i].knob.set_target(group.knob_value);
+        }
+
+        if let LedMode::External = self.parameters.led_mode {
+            for i in 0..8 {
+                let group = data.groups[i as usize];
+                self.send_button_value(ControlId::Solo(i), group.solo)?;
+                self.send_button_value(ControlId::Mute(i), group.mute)?;
+                self.send_button_value(ControlId::Record(i), group.record)?;
+            }
+            for &button_type in TransportButton::ALL.iter() {
+                let control = ControlId::Transport(button_type);
+                if Some(control) != self.shift.get_button() {
+                    self.send_button_value(control, data.get_transport_button_value(button_type))?;
+                }
+            }
+        }
         Ok(())
     }
 
+    /// Lights or clears a button LED in the active layer. The device only follows this in external
+    /// LED mode.
+    pub fn set_button_led(&mut self, control: ControlId, on: bool) -> Result<()> {
+        let value = match self.parameters.get_button_value(control, on) {
+            Some(value) => value,
+            None => return Ok(()),
+        };
+        self.get_layer_data_mut().set_value(control, value);
+        self.send_button_value(control, value)
+    }
+
+    /// A cloneable sender for the device's output, which can be moved into subscriptions and
+    /// other callbacks. LEDs lit through it are not reflected in the control values.
+    pub fn get_output_sender(&mut self) -> OutputSender {
+        self.connection.get_output_sender()
+    }
+
+    fn send_button_value(&mut self, control: ControlId, value: U7) -> Result<()> {
+        let (assign_type, note_number) = match self.parameters.get_button_parameters(control) {
+            Some(button_parameters) => (button_parameters.assign_type, button_parameters.note_number),
+            None => return Ok(()),
+        };
+        let midi_channel = self.parameters.get_control_channel(control);
+
+        match assign_type {
+            ButtonAssignType::ControlChange =>
+                self.connection.send_control_change(midi_channel, note_number.into(), value),
+            ButtonAssignType::Note => self.connection.send_note(midi_channel, note_number, value),
+            ButtonAssignType::NoAssign => Ok(()),
+        }
+    }
+
+    pub fn get_gestures(&self) -> &GestureRecognizer {
+        &self.gestures
+    }
+
+    pub fn get_gestures_mut(&mut self) -> &mut GestureRecognizer {
+        &mut self.gestures
+    }
+
+    pub fn get_motion(&self) -> &MotionTracker {
+        &self.motion
+    }
+
+    pub fn get_motion_mut(&mut self) -> &mut MotionTracker {
+        &mut self.motion
+    }
+
+    pub fn get_parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+
+    /// The lookup table for the current scene, rebuilt whenever the scene changes.
+    pub fn get_lookup(&self) -> &ControlLookup {
+        &self.lookup
+    }
+
+    pub fn get_data(&self) -> &Data {
+        &self.data
+    }
+
     pub fn get_slider_value(&self, group_index: usize) -> f32 {
         let value = self.data.groups[group_index].slider_value;
         let slider_parameters = &self.parameters.groups[group_index].slider;
-        get_continuous_value(value, slider_parameters)
+        get_continuous_value(value, slider_parameters, &self.slider_mappings[group_index])
     }
 
-    pub fn get_slider_value_raw(&self, group_index: usize) -> u8 {
+    pub fn get_slider_value_raw(&self, group_index: usize) -> U7 {
         self.data.groups[group_index].slider_value
     }
 
     pub fn get_knob_value(&self, group_index: usize) -> f32 {
         let value = self.data.groups[group_index].knob_value;
         let slider_parameters = &self.parameters.groups[group_index].knob;
-        get_continuous_value(value, slider_parameters)
+        get_continuous_value(value, slider_parameters, &self.knob_mappings[group_index])
     }
 
-    pub fn get_knob_value_raw(&self, group_index: usize) -> u8 {
+    pub fn get_knob_value_raw(&self, group_index: usize) -> U7 {
         self.data.groups[group_index].knob_value
     }
 
+    pub fn get_slider_mapping(&self, group_index: usize) -> &ValueMapping {
+        &self.slider_mappings[group_index]
+    }
+
+    pub fn set_slider_mapping(&mut self, group_index: usize, mapping: ValueMapping) {
+        self.slider_mappings[group_index] = mapping;
+    }
+
+    pub fn get_knob_mapping(&self, group_index: usize) -> &ValueMapping {
+        &self.knob_mappings[group_index]
+    }
+
+    pub fn set_knob_mapping(&mut self, group_index: usize, mapping: ValueMapping) {
+        self.knob_mappings[group_index] = mapping;
+    }
+
+    pub fn set_slider_takeover_mode(&mut self, group_index: usize, mode: TakeoverMode) {
+        self.takeover[group_index].slider.mode = mode;
+    }
+
+    pub fn set_knob_takeover_mode(&mut self, group_index: usize, mode: TakeoverMode) {
+        self.takeover[group_index].knob.mode = mode;
+    }
+
+    /// Sets the slider value from software. The physical slider takes over again according to its
+    /// `TakeoverMode`.
+    pub fn set_slider_value_raw(&mut self, group_index: usize, value: U7) {
+        self.takeover[group_index].slider.set_target(value);
+        self.data.groups[group_index].slider_value = value;
+    }
+
+    /// Sets the knob value from software. The physical knob takes over again according to its
+    /// `TakeoverMode`.
+    pub fn set_knob_value_raw(&mut self, group_index: usize, value: U7) {
+        self.takeover[group_index].knob.set_target(value);
+        self.data.groups[group_index].knob_value = value;
+    }
+
+    pub fn is_slider_synchronized(&self, group_index: usize) -> bool {
+        self.takeover[group_index].slider.is_synchronized()
+    }
+
+    pub fn is_knob_synchronized(&self, group_index: usize) -> bool {
+        self.takeover[group_index].knob.is_synchronized()
+    }
+
+    /// When the control's value was last received, `None` if it was never received.
+    pub fn get_timestamp(&self, control: ControlId) -> Option<u64> {
+        self.timestamps[control.get_index()]
+    }
+
+    /// A copy of the values of every control along with their timestamps.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            data: self.data.clone(),
+            timestamps: self.timestamps,
+        }
+    }
+
+    /// The raw value of any control.
+    pub fn get_value_raw(&self, control: ControlId) -> U7 {
+        self.data.get_value(control)
+    }
+
+    /// Whether a button is pressed, or lit for toggle buttons. Always false for sliders and knobs.
+    pub fn get_button_state(&self, control: ControlId) -> bool {
+        let value = self.data.get_value(control);
+        self.parameters.get_button_parameters(control)
+            .is_some_and(|button_parameters| get_button_state(value, button_parameters))
+    }
+
     pub fn get_transport_button_state(&self, button_type: TransportButton) -> bool {
-        let value = self.data.get_transport_button_value(button_type);
-        let button_parameters = self.parameters.get_transport_button_parameters(button_type);
-        get_button_state(value, button_parameters)
+        self.get_button_state(ControlId::Transport(button_type))
     }
 
     pub fn get_solo_button_state(&self, group_index: usize) -> bool {
-        let value = self.data.groups[group_index].solo;
-        let button_parameters = &self.parameters.groups[group_index].solo_button;
-        get_button_state(value, button_parameters)
+        self.get_button_state(ControlId::Solo(group_index as u8))
     }
 
     pub fn get_mute_button_state(&self, group_index: usize) -> bool {
-        let value = self.data.groups[group_index].mute;
-        let button_parameters = &self.parameters.groups[group_index].mute_button;
-        get_button_state(value, button_parameters)
+        self.get_button_state(ControlId::Mute(group_index as u8))
     }
 
     pub fn get_record_button_state(&self, group_index: usize) -> bool {
-        let value = self.data.groups[group_index].record;
-        let button_parameters = &self.parameters.groups[group_index].record_button;
-        get_button_state(value, button_parameters)
+        self.get_button_state(ControlId::Record(group_index as u8))
     }
 }
 
-fn get_continuous_value(value: u8, slider_parameters: &SliderParameters) -> f32 {
-    let min_value = slider_parameters.min_value;
-    let max_value = slider_parameters.max_value;
-
-    let range = max_value - min_value;
-
-    match range {
-        0 => 0.0,
-        range => (value - min_value) as f32 / range as f32,
+#[cfg(test)]
+impl KorgNanokontrol2 {
+    /// Queues a message as if the device had sent it, handled by the next `poll_events`.
+    pub(crate) fn receive(&self, timestamp: u64, message: &[u8]) {
+        self.input_sender.send(Input { timestamp, message: message.to_vec() }).ok();
     }
 }
 
-fn get_button_state(value: u8, button_parameters: &ButtonParameters) -> bool {
-    let off_value = button_parameters.off_value;
-    let on_value = button_parameters.on_value;
+fn get_continuous_value(value: U7, slider_parameters: &SliderParameters, mapping: &ValueMapping)
+-> f32 {
+    let normalized = curve::normalize(value, slider_parameters.min_value,
+        slider_parameters.max_value);
+    mapping.apply(normalized)
+}
+
+fn get_button_state(value: U7, button_parameters: &ButtonParameters) -> bool {
+    let off_value = button_parameters.off_value.get();
+    let on_value = button_parameters.on_value.get();
 
-    match value {
+    match value.get() {
         n if n == off_value => false,
         n if n == on_value => true,
         n => {
             // use the Hamming distance to pick a value, favoring false when equal
             let off_distance = (n ^ off_value).count_zeros();
             let on_distance = (n ^ on_value).count_zeros();
-            (off_distance < on_distance)
+            off_distance < on_distance
         }
     }
 }
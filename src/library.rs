@@ -0,0 +1,284 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::error::Error;
+use super::parameters::Parameters;
+use super::session::{from_hex, to_hex};
+use super::{KorgNanokontrol2, Result};
+
+const HEADER: &str = "# korgnanokontrol2 scene v1";
+const EXTENSION: &str = "nk2scene";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SceneEntry {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub parameters: Parameters,
+}
+
+impl SceneEntry {
+    pub fn fingerprint(&self) -> u64 {
+        fingerprint(&self.parameters)
+    }
+}
+
+/// A directory of named scenes, stored one scene per file along with its tags.
+#[derive(Debug, Clone)]
+pub struct SceneLibrary {
+    directory: PathBuf,
+}
+
+impl SceneLibrary {
+    /// Opens the library in `directory`, creating the directory if it does not exist.
+    pub fn open<P: AsRef<Path>>(directory: P) -> Result<Self> {
+        fs::create_dir_all(&directory)?;
+        Ok(SceneLibrary {
+            directory: directory.as_ref().to_path_buf(),
+        })
+    }
+
+    pub fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Returns the names of all scenes in the library, sorted.
+    pub fn list(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some(EXTENSION) {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get_path(name).is_ok_and(|path| path.is_file())
+    }
+
+    pub fn load(&self, name: &str) -> Result<SceneEntry> {
+        let path = self.get_existing_path(name)?;
+        let invalid = || Error::InvalidSceneFile(name.to_string());
+
+        let mut tags = Vec::new();
+        let mut scene = None;
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            let mut fields = line.split('\t');
+            match fields.next() {
+                Some("tags") => tags.extend(fields.filter(|tag| !tag.is_empty()).map(str::to_string)),
+                Some("scene") => scene = Some(fields.next().and_then(from_hex).ok_or_else(invalid)?),
+                _ => (),
+            }
+        }
+
+        let scene = scene.ok_or_else(invalid)?;
+        Ok(SceneEntry {
+            name: name.to_string(),
+            tags,
+            parameters: Parameters::parse_scene_dump(&scene)?,
+        })
+    }
+
+    /// Saves a scene, replacing any scene with the same name. Tags cannot be empty or contain
+    /// tabs or other control characters.
+    pub fn save(&self, name: &str, parameters: &Parameters, tags: &[String]) -> Result<()> {
+        let path = self.get_path(name)?;
+        if let Some(tag) = tags.iter().find(|tag| !is_valid_tag(tag)) {
+            return Err(Error::InvalidSceneTag(tag.clone()));
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{}", HEADER)?;
+        writeln!(writer, "tags\t{}", tags.join("\t"))?;
+        writeln!(writer, "scene\t{}", to_hex(&parameters.create_scene_dump()))?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn remove(&self, name: &str) -> Result<()> {
+        fs::remove_file(self.get_existing_path(name)?)?;
+        Ok(())
+    }
+
+    pub fn rename(&self, name: &str, new_name: &str) -> Result<()> {
+        let path = self.get_existing_path(name)?;
+        let new_path = self.get_path(new_name)?;
+        if new_path.exists() {
+            return Err(Error::SceneAlreadyExists(new_name.to_string()));
+        }
+        fs::rename(path, new_path)?;
+        Ok(())
+    }
+
+    pub fn set_tags(&self, name: &str, tags: &[String]) -> Result<()> {
+        let entry = self.load(name)?;
+        self.save(name, &entry.parameters, tags)
+    }
+
+    pub fn add_tag(&self, name: &str, tag: &str) -> Result<()> {
+        let mut entry = self.load(name)?;
+        if !entry.tags.iter().any(|existing| existing == tag) {
+            entry.tags.push(tag.to_string());
+        }
+        self.save(name, &entry.parameters, &entry.tags)
+    }
+
+    pub fn remove_tag(&self, name: &str, tag: &str) -> Result<()> {
+        let mut entry = self.load(name)?;
+        entry.tags.retain(|existing| existing != tag);
+        self.save(name, &entry.parameters, &entry.tags)
+    }
+
+    /// Returns the names of all scenes carrying `tag`. Scenes that cannot be loaded are skipped.
+    pub fn find_by_tag(&self, tag: &str) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in self.load_all()? {
+            if entry.tags.iter().any(|existing| existing == tag) {
+                names.push(entry.name);
+            }
+        }
+        Ok(names)
+    }
+
+    /// Returns the name of the first scene with the same fingerprint as `parameters`. Scenes that
+    /// cannot be loaded are skipped.
+    pub fn find_match(&self, parameters: &Parameters) -> Result<Option<String>> {
+        let target = fingerprint(parameters);
+        let entry = self.load_all()?.into_iter().find(|entry| entry.fingerprint() == target);
+        Ok(entry.map(|entry| entry.name))
+    }
+
+    /// Returns the name of the library scene matching the scene currently on the device.
+    pub fn find_current(&self, device: &KorgNanokontrol2) -> Result<Option<String>> {
+        self.find_match(device.get_parameters())
+    }
+
+    /// Writes a library scene to the device.
+    pub fn apply(&self, name: &str, device: &mut KorgNanokontrol2) -> Result<()> {
+        let entry = self.load(name)?;
+        device.write_scene(entry.parameters)
+    }
+
+    /// Loads every scene in the library, leaving out the ones that cannot be loaded so that one
+    /// broken file does not hide the rest.
+    fn load_all(&self) -> Result<Vec<SceneEntry>> {
+        Ok(self.list()?.iter().filter_map(|name| self.load(name).ok()).collect())
+    }
+
+    fn get_path(&self, name: &str) -> Result<PathBuf> {
+        let is_valid = !name.is_empty()
+            && !name.starts_with('.')
+            && !name.contains(|c: char| c == '/' || c == '\\' || c.is_control());
+        match is_valid {
+            true => Ok(self.directory.join(format!("{}.{}", name, EXTENSION))),
+            false => Err(Error::InvalidSceneName(name.to_string())),
+        }
+    }
+
+    fn get_existing_path(&self, name: &str) -> Result<PathBuf> {
+        let path = self.get_path(name)?;
+        match path.is_file() {
+            true => Ok(path),
+            false => Err(Error::SceneNotFound(name.to_string())),
+        }
+    }
+}
+
+fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty() && !tag.contains(char::is_control)
+}
+
+/// A 64-bit FNV-1a hash of the scene dump, identifying scenes with identical settings.
+pub fn fingerprint(parameters: &Parameters) -> u64 {
+    parameters.create_scene_dump().iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(name: &str) -> SceneLibrary {
+        let directory = std::env::temp_dir()
+            .join(format!("korgnanokontrol2-{}-{}", std::process::id(), name));
+        fs::remove_dir_all(&directory).ok();
+        SceneLibrary::open(directory).unwrap()
+    }
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn save_and_load() {
+        let library = library("save-and-load");
+        let parameters = Parameters::sequential_cc();
+        library.save("live", &parameters, &tags(&["stage", "drums"])).unwrap();
+
+        assert_eq!(library.list().unwrap(), vec!["live".to_string()]);
+        assert_eq!(library.load("live").unwrap(), SceneEntry {
+            name: "live".to_string(),
+            tags: tags(&["stage", "drums"]),
+            parameters,
+        });
+        assert!(matches!(library.load("studio"), Err(Error::SceneNotFound(_))));
+        assert!(matches!(library.save("../live", &Parameters::factory_default(), &[]),
+            Err(Error::InvalidSceneName(_))));
+        fs::remove_dir_all(library.get_directory()).ok();
+    }
+
+    #[test]
+    fn rename_onto_an_existing_name() {
+        let library = library("rename");
+        library.save("live", &Parameters::sequential_cc(), &[]).unwrap();
+        library.save("studio", &Parameters::factory_default(), &[]).unwrap();
+
+        assert!(matches!(library.rename("live", "studio"),
+            Err(Error::SceneAlreadyExists(_))));
+        assert_eq!(library.load("studio").unwrap().parameters, Parameters::factory_default());
+        library.rename("live", "stage").unwrap();
+        assert_eq!(library.list().unwrap(), vec!["stage".to_string(), "studio".to_string()]);
+        fs::remove_dir_all(library.get_directory()).ok();
+    }
+
+    #[test]
+    fn add_and_remove_tags() {
+        let library = library("tags");
+        library.save("live", &Parameters::sequential_cc(), &[]).unwrap();
+        library.save("studio", &Parameters::factory_default(), &[]).unwrap();
+        library.add_tag("live", "stage").unwrap();
+        library.add_tag("live", "stage").unwrap();
+        library.add_tag("studio", "stage").unwrap();
+        library.remove_tag("studio", "stage").unwrap();
+
+        assert_eq!(library.load("live").unwrap().tags, tags(&["stage"]));
+        assert_eq!(library.find_by_tag("stage").unwrap(), vec!["live".to_string()]);
+        for tag in ["", "live\tset", "live\nscene\t00"] {
+            assert!(matches!(library.add_tag("live", tag), Err(Error::InvalidSceneTag(_))));
+        }
+        fs::remove_dir_all(library.get_directory()).ok();
+    }
+
+    #[test]
+    fn find_match_skips_broken_files() {
+        let library = library("find-match");
+        fs::write(library.get_directory().join("broken.nk2scene"), "scene\tzz\n").unwrap();
+        library.save("live", &Parameters::sequential_cc(), &tags(&["stage"])).unwrap();
+        library.save("studio", &Parameters::factory_default(), &[]).unwrap();
+
+        assert_eq!(library.find_match(&Parameters::factory_default()).unwrap(),
+            Some("studio".to_string()));
+        assert_eq!(library.find_match(&Parameters::sequential_cc()).unwrap(),
+            Some("live".to_string()));
+        assert_eq!(library.find_match(&Parameters::default()).unwrap(), None);
+        assert_eq!(library.find_by_tag("stage").unwrap(), vec!["live".to_string()]);
+        fs::remove_dir_all(library.get_directory()).ok();
+    }
+}
@@ -0,0 +1,141 @@
+use std::fmt;
+
+use super::control::ControlId;
+use super::enums::*;
+use super::parameters::Parameters;
+use super::types::{CcNumber, Channel, U7};
+
+/// The kind of channel message a control is assigned to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MessageType {
+    ControlChange,
+    Note,
+}
+
+/// Several controls assigned to the same message. Only the first one is reported by the lookup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmbiguousAssignment {
+    pub midi_channel: Channel,
+    pub message_type: MessageType,
+    pub number: U7,
+    pub controls: Vec<ControlId>,
+}
+
+impl fmt::Display for AmbiguousAssignment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let controls: Vec<String> = self.controls.iter().map(ControlId::to_string).collect();
+        let message_type = match self.message_type {
+            MessageType::ControlChange => "CC",
+            MessageType::Note => "note",
+        };
+        write!(f, "{} share {} {} on channel {}.", controls.join(", "), message_type,
+            self.number.get(), self.midi_channel.get() + 1)
+    }
+}
+
+/// A table from the channel and number of an incoming message to the control assigned to it,
+/// built by `Parameters::build_lookup`.
+///
+/// When several controls share a message, the first one in group order wins, the slider, knob,
+/// solo, mute and record of each group coming before the transport buttons.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlLookup {
+    controls: Vec<Option<ControlId>>,
+    ambiguities: Vec<AmbiguousAssignment>,
+}
+
+impl ControlLookup {
+    pub fn new(parameters: &Parameters) -> Self {
+        let mut lookup = ControlLookup {
+            controls: vec![None; 16 * 2 * 128],
+            ambiguities: Vec::new(),
+        };
+
+        for i in 0..8 {
+            for &control in [ControlId::Slider(i), ControlId::Knob(i), ControlId::Solo(i),
+                ControlId::Mute(i), ControlId::Record(i)].iter() {
+                lookup.insert(parameters, control);
+            }
+        }
+        for &button_type in TransportButton::ALL.iter() {
+            lookup.insert(parameters, ControlId::Transport(button_type));
+        }
+        lookup
+    }
+
+    pub fn get(&self, midi_channel: Channel, message_type: MessageType, number: U7)
+    -> Option<ControlId> {
+        self.controls[get_index(midi_channel, message_type, number)]
+    }
+
+    pub fn find_control_change(&self, midi_channel: Channel, control_change: CcNumber)
+    -> Option<ControlId> {
+        self.get(midi_channel, MessageType::ControlChange, control_change.into())
+    }
+
+    pub fn find_note(&self, midi_channel: Channel, note_number: U7) -> Option<ControlId> {
+        self.get(midi_channel, MessageType::Note, note_number)
+    }
+
+    /// The messages assigned to more than one control.
+    pub fn get_ambiguities(&self) -> &[AmbiguousAssignment] {
+        &self.ambiguities
+    }
+
+    pub fn is_ambiguous(&self) -> bool {
+        !self.ambiguities.is_empty()
+    }
+
+    fn insert(&mut self, parameters: &Parameters, control: ControlId) {
+        let (message_type, number) = match get_assignment(parameters, control) {
+            Some(assignment) => assignment,
+            None => return,
+        };
+        let midi_channel = parameters.get_control_channel(control);
+        let index = get_index(midi_channel, message_type, number);
+
+        let existing = match self.controls[index] {
+            Some(existing) => existing,
+            None => {
+                self.controls[index] = Some(control);
+                return;
+            },
+        };
+
+        let ambiguity = self.ambiguities.iter_mut().find(|ambiguity| {
+            ambiguity.midi_channel == midi_channel && ambiguity.message_type == message_type
+                && ambiguity.number == number
+        });
+        match ambiguity {
+            Some(ambiguity) => ambiguity.controls.push(control),
+            None => self.ambiguities.push(AmbiguousAssignment {
+                midi_channel,
+                message_type,
+                number,
+                controls: vec![existing, control],
+            }),
+        }
+    }
+}
+
+fn get_assignment(parameters: &Parameters, control: ControlId) -> Option<(MessageType, U7)> {
+    if let Some(slider_parameters) = parameters.get_slider_parameters(control) {
+        return match slider_parameters.assign_type {
+            SliderAssignType::Enable =>
+                Some((MessageType::ControlChange, slider_parameters.note_number.into())),
+            SliderAssignType::Disable => None,
+        };
+    }
+
+    let button_parameters = parameters.get_button_parameters(control)?;
+    match button_parameters.assign_type {
+        ButtonAssignType::ControlChange =>
+            Some((MessageType::ControlChange, button_parameters.note_number)),
+        ButtonAssignType::Note => Some((MessageType::Note, button_parameters.note_number)),
+        ButtonAssignType::NoAssign => None,
+    }
+}
+
+fn get_index(midi_channel: Channel, message_type: MessageType, number: U7) -> usize {
+    ((midi_channel.get() as usize * 2) + message_type as usize) * 128 + number.get() as usize
+}
@@ -0,0 +1,215 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+
+use super::control::ControlId;
+use super::gesture::{Gesture, GestureEvent};
+use super::types::U7;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Increasing,
+    Decreasing,
+}
+
+/// How a slider or knob is moving, derived from the timestamps of its recent values.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Motion {
+    /// Value steps per second over the velocity window, negative while decreasing.
+    pub velocity: f32,
+    /// Change in velocity per second since the previous value.
+    pub acceleration: f32,
+    /// `None` until the control moves again after being idle.
+    pub direction: Option<Direction>,
+    /// Whether the control turned back with this value.
+    pub reversed: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MotionConfig {
+    /// Number of microseconds of recent values the velocity is measured over.
+    pub velocity_window: u64,
+    /// Number of microseconds without values after which a control counts as idle again.
+    pub idle_timeout: u64,
+    /// Minimum speed in value steps per second for a movement to count as a flick.
+    pub flick_speed: f32,
+}
+
+impl Default for MotionConfig {
+    fn default() -> Self {
+        MotionConfig {
+            velocity_window: 50_000,
+            idle_timeout: 100_000,
+            flick_speed: 1000.0,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct MotionState {
+    samples: VecDeque<(u64, u8)>,
+    velocity: f32,
+    direction: Option<Direction>,
+    flicked: bool,
+}
+
+/// Tracks the motion of sliders and knobs.
+///
+/// A movement lasts until the control turns back or stays idle for the idle timeout. Each
+/// movement reports at most one flick, as soon as its speed reaches the flick speed.
+#[derive(Debug, Default)]
+pub struct MotionTracker {
+    pub config: MotionConfig,
+    states: HashMap<ControlId, MotionState>,
+}
+
+impl MotionTracker {
+    pub fn new(config: MotionConfig) -> Self {
+        MotionTracker {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Forgets every movement, e.g. after values jumped without the controls moving.
+    pub fn reset(&mut self) {
+        self.states.clear();
+    }
+
+    /// Feeds a value of a slider or knob, returning its motion and the flick it completes.
+    pub fn process(&mut self, timestamp: u64, control: ControlId, value: U7)
+    -> (Motion, Option<GestureEvent>) {
+        let config = self.config;
+        let state = self.states.entry(control).or_default();
+
+        let idle = state.samples.back()
+            .is_none_or(|&(last_timestamp, _)| {
+                timestamp.saturating_sub(last_timestamp) > config.idle_timeout
+            });
+        if idle {
+            *state = MotionState::default();
+        }
+        let previous = state.samples.back().copied();
+        let direction = match previous.map(|(_, previous_value)| value.get().cmp(&previous_value)) {
+            Some(Ordering::Greater) => Some(Direction::Increasing),
+            Some(Ordering::Less) => Some(Direction::Decreasing),
+            _ => None,
+        };
+        let reversed = match (state.direction, direction) {
+            (Some(last_direction), Some(direction)) => last_direction != direction,
+            _ => false,
+        };
+        if reversed {
+            // a new movement starts from the previous value
+            state.samples.drain(..state.samples.len() - 1);
+            state.flicked = false;
+        }
+        if direction.is_some() {
+            state.direction = direction;
+        }
+
+        state.samples.push_back((timestamp, value.get()));
+        while state.samples.len() > 2 && state.samples.front()
+            .is_some_and(|&(first_timestamp, _)| {
+                timestamp.saturating_sub(first_timestamp) > config.velocity_window
+            }) {
+            state.samples.pop_front();
+        }
+
+        let (first_timestamp, first_value) = state.samples[0];
+        let velocity = match timestamp.saturating_sub(first_timestamp) {
+            0 => state.velocity,
+            elapsed => (value.get() as f32 - first_value as f32) / seconds(elapsed),
+        };
+        let acceleration = match previous {
+            Some((previous_timestamp, _)) if timestamp > previous_timestamp =>
+                (velocity - state.velocity) / seconds(timestamp - previous_timestamp),
+            _ => 0.0,
+        };
+        state.velocity = velocity;
+
+        let flick = match state.direction {
+            Some(direction) if !state.flicked && velocity.abs() >= config.flick_speed => {
+                state.flicked = true;
+                Some(GestureEvent {
+                    timestamp,
+                    gesture: Gesture::Flick {
+                        control,
+                        direction,
+                        speed: velocity.abs() as u32,
+                    },
+                })
+            },
+            _ => None,
+        };
+
+        let motion = Motion {
+            velocity,
+            acceleration,
+            direction: state.direction,
+            reversed,
+        };
+        (motion, flick)
+    }
+}
+
+fn seconds(microseconds: u64) -> f32 {
+    microseconds as f32 / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Event;
+    use crate::KorgNanokontrol2;
+
+    const SLIDER_1: u8 = 0x00;
+
+    /// Moves the first slider through `values`, one every 10 ms.
+    fn move_slider(device: &KorgNanokontrol2, values: &[u8]) {
+        for (i, &value) in values.iter().enumerate() {
+            device.receive(i as u64 * 10_000, &[0xB0, SLIDER_1, value]);
+        }
+    }
+
+    fn flicks(events: Vec<Event>) -> Vec<(u64, Direction)> {
+        events.into_iter()
+            .filter_map(|event| match event {
+                Event::Gesture(GestureEvent {
+                    timestamp,
+                    gesture: Gesture::Flick { direction, .. },
+                }) => Some((timestamp, direction)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn one_flick_per_movement() {
+        let mut device = KorgNanokontrol2::new();
+        move_slider(&device, &[0, 20, 40, 60, 80]);
+        assert_eq!(flicks(device.poll_events()), vec![(10_000, Direction::Increasing)]);
+    }
+
+    #[test]
+    fn turning_back_starts_a_new_movement() {
+        let mut device = KorgNanokontrol2::new();
+        move_slider(&device, &[0, 20, 40, 20, 0]);
+        let events = device.poll_events();
+        let reversed: Vec<_> = events.iter()
+            .filter_map(|event| match event {
+                Event::Control(event) => event.motion.map(|motion| motion.reversed),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(reversed, vec![false, false, false, true, false]);
+        assert_eq!(flicks(events),
+            vec![(10_000, Direction::Increasing), (30_000, Direction::Decreasing)]);
+    }
+
+    #[test]
+    fn slow_movements_do_not_flick() {
+        let mut device = KorgNanokontrol2::new();
+        move_slider(&device, &[0, 2, 4, 6]);
+        assert_eq!(flicks(device.poll_events()), Vec::new());
+    }
+}
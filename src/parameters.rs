@@ -1,26 +1,67 @@
-use std::fmt::Display;
-
 use super::*;
-use super::error::Error;
+use super::control::ControlId;
+use super::lookup::ControlLookup;
+use super::types::{CcNumber, Channel, GlobalChannel, U7};
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct ButtonParameters {
     pub assign_type: ButtonAssignType,
     pub behavior: ButtonBehavior,
-    pub note_number: u8,
-    pub off_value: u8,
-    pub on_value: u8,
+    pub note_number: U7,
+    pub off_value: U7,
+    pub on_value: U7,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct SliderParameters {
     pub assign_type: SliderAssignType,
-    pub note_number: u8,
-    pub min_value: u8,
-    pub max_value: u8,
+    pub note_number: CcNumber,
+    pub min_value: U7,
+    pub max_value: U7,
 }
 
-#[derive(Default, Debug)]
+impl ButtonParameters {
+    /// A momentary button sending `note_number` as a control change, 0 when released and 127 when
+    /// pressed.
+    pub fn control_change(note_number: U7) -> Self {
+        ButtonParameters {
+            assign_type: ButtonAssignType::ControlChange,
+            behavior: ButtonBehavior::Momentary,
+            note_number,
+            off_value: U7::MIN,
+            on_value: U7::MAX,
+        }
+    }
+
+    /// A momentary button sending `note_number` as a note, with a velocity of 127 when pressed.
+    pub fn note(note_number: U7) -> Self {
+        ButtonParameters {
+            assign_type: ButtonAssignType::Note,
+            ..Self::control_change(note_number)
+        }
+    }
+}
+
+impl SliderParameters {
+    /// A control sending `note_number` as a control change over the full 0-127 range.
+    pub fn control_change(note_number: CcNumber) -> Self {
+        SliderParameters {
+            assign_type: SliderAssignType::Enable,
+            note_number,
+            min_value: U7::MIN,
+            max_value: U7::MAX,
+        }
+    }
+}
+
+/// The settings of any control, as returned by `Parameters::get_control_parameters`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ControlParameters<'a> {
+    Slider(&'a SliderParameters),
+    Button(&'a ButtonParameters),
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct ControllerGroupParameters {
     pub channel: MidiChannel,
     pub slider: SliderParameters,
@@ -30,9 +71,9 @@ pub struct ControllerGroupParameters {
     pub record_button: ButtonParameters,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct Parameters {
-    pub global_channel: u8,
+    pub global_channel: GlobalChannel,
     pub control_mode: ControlMode,
     pub led_mode: LedMode,
     pub groups: [ControllerGroupParameters; 8],
@@ -48,18 +89,98 @@ pub struct Parameters {
     pub stop:               ButtonParameters,
     pub play:               ButtonParameters,
     pub record:             ButtonParameters,
-    pub custom_daw_assign: [u8; 5],
+    pub custom_daw_assign: [U7; 5],
 }
 
 impl Parameters {
-    pub fn parse_scene_dump<'a>(dump: &[u8]) -> Result<Self> {
+    /// The scene the nanoKONTROL2 ships with: everything on global channel 1, with the sliders on
+    /// CC 0-7, the knobs on CC 16-23, the solo, mute and record buttons on CC 32-39, 48-55 and
+    /// 64-71, and the transport buttons on CC 41-46 and 58-62.
+    pub fn factory_default() -> Self {
+        let mut parameters = Parameters::default();
+        for (i, group) in parameters.groups.iter_mut().enumerate() {
+            let i = i as u8;
+            group.slider = SliderParameters::control_change(CcNumber::from_const(i));
+            group.knob = SliderParameters::control_change(CcNumber::from_const(16 + i));
+            group.solo_button = ButtonParameters::control_change(U7::from_const(32 + i));
+            group.mute_button = ButtonParameters::control_change(U7::from_const(48 + i));
+            group.record_button = ButtonParameters::control_change(U7::from_const(64 + i));
+        }
+
+        parameters.track_rewind       = ButtonParameters::control_change(U7::from_const(58));
+        parameters.track_fastforward  = ButtonParameters::control_change(U7::from_const(59));
+        parameters.cycle              = ButtonParameters::control_change(U7::from_const(46));
+        parameters.set                = ButtonParameters::control_change(U7::from_const(60));
+        parameters.marker_rewind      = ButtonParameters::control_change(U7::from_const(61));
+        parameters.marker_fastforward = ButtonParameters::control_change(U7::from_const(62));
+        parameters.rewind             = ButtonParameters::control_change(U7::from_const(43));
+        parameters.fastforward        = ButtonParameters::control_change(U7::from_const(44));
+        parameters.stop               = ButtonParameters::control_change(U7::from_const(42));
+        parameters.play               = ButtonParameters::control_change(U7::from_const(41));
+        parameters.record             = ButtonParameters::control_change(U7::from_const(45));
+        parameters
+    }
+
+    /// Every control on channel 1 with consecutive CC numbers from 0: the sliders, then the knobs,
+    /// the solo, mute and record buttons, and finally the transport buttons in panel order.
+    pub fn sequential_cc() -> Self {
+        let mut parameters = Self::factory_default();
+        parameters.assign_sequential_cc(0).expect("CC numbers 0-50 are valid");
+        parameters
+    }
+
+    /// Assigns consecutive CC numbers from `first_control_change` to every control in the order of
+    /// `ControlId::ALL`. Assignment types are switched to control changes, other settings are
+    /// kept. Fails when the last control would not get a valid CC number.
+    pub fn assign_sequential_cc(&mut self, first_control_change: u8) -> Result<()> {
+        let last_control_change = first_control_change as usize + ControlId::ALL.len() - 1;
+        CcNumber::new(last_control_change.min(u8::MAX as usize) as u8)?;
+
+        for (i, control) in ControlId::iter().enumerate() {
+            let control_change = CcNumber::from_masked(first_control_change + i as u8);
+            if let Some(slider) = self.get_slider_parameters_mut(control) {
+                assign_slider_cc(slider, control_change);
+            } else if let Some(button) = self.get_button_parameters_mut(control) {
+                assign_button_cc(button, control_change);
+            }
+        }
+        Ok(())
+    }
+
+    /// The factory scene with the strip buttons sending notes for triggering drums. The record
+    /// row starts at note 36 (C1), followed by the mute row from 44 and the solo row from 52.
+    pub fn drum_triggers() -> Self {
+        let mut parameters = Self::factory_default();
+        for (i, group) in parameters.groups.iter_mut().enumerate() {
+            let i = i as u8;
+            group.record_button = ButtonParameters::note(U7::from_const(36 + i));
+            group.mute_button = ButtonParameters::note(U7::from_const(44 + i));
+            group.solo_button = ButtonParameters::note(U7::from_const(52 + i));
+        }
+        parameters
+    }
+
+    /// The factory scene with toggling strip buttons whose LEDs are driven by the host.
+    pub fn external_led_toggles() -> Self {
+        let mut parameters = Self::factory_default();
+        parameters.led_mode = LedMode::External;
+        for group in parameters.groups.iter_mut() {
+            group.solo_button.behavior = ButtonBehavior::Toggle;
+            group.mute_button.behavior = ButtonBehavior::Toggle;
+            group.record_button.behavior = ButtonBehavior::Toggle;
+        }
+        parameters
+    }
+
+    /// Parses the 389 bytes of a scene data dump, starting after the dump header.
+    pub fn parse_scene_dump(dump: &[u8]) -> Result<Self> {
+        if dump.len() < 389 {
+            return Err(Error::InvalidSceneDump(dump.len()));
+        }
         let mut parsed = Self::default();
 
         let global_channel_val = dump[index_to_data_dump_index(0)];
-        parsed.global_channel = match global_channel_val {
-            n if n < 16 => n,
-            n => return Err(Error::InvalidGlobalChannel(n)),
-        };
+        parsed.global_channel = GlobalChannel::new(global_channel_val)?;
 
         let control_mode_val = dump[index_to_data_dump_index(1)];
         parsed.control_mode = ControlMode::from(control_mode_val);
@@ -69,27 +190,27 @@ impl Parameters {
 
         for i in 0..8 {
             let index: usize = 3 + (i * 31);
-            parsed.groups[i] = parse_group_data(&dump, index);
+            parsed.groups[i] = parse_group_data(dump, index)?;
         }
 
         let transport_button_channel_val = dump[index_to_data_dump_index(251)];
         parsed.transport_button_channel = MidiChannel::from(transport_button_channel_val);
 
-        parsed.track_rewind       = parse_button_data(&dump, 252);
-        parsed.track_fastforward  = parse_button_data(&dump, 258);
-        parsed.cycle              = parse_button_data(&dump, 264);
-        parsed.set                = parse_button_data(&dump, 270);
-        parsed.marker_rewind      = parse_button_data(&dump, 276);
-        parsed.marker_fastforward = parse_button_data(&dump, 282);
-        parsed.rewind             = parse_button_data(&dump, 288);
-        parsed.fastforward        = parse_button_data(&dump, 294);
-        parsed.stop               = parse_button_data(&dump, 300);
-        parsed.play               = parse_button_data(&dump, 306);
-        parsed.record             = parse_button_data(&dump, 312);
+        parsed.track_rewind       = parse_button_data(dump, 252)?;
+        parsed.track_fastforward  = parse_button_data(dump, 258)?;
+        parsed.cycle              = parse_button_data(dump, 264)?;
+        parsed.set                = parse_button_data(dump, 270)?;
+        parsed.marker_rewind      = parse_button_data(dump, 276)?;
+        parsed.marker_fastforward = parse_button_data(dump, 282)?;
+        parsed.rewind             = parse_button_data(dump, 288)?;
+        parsed.fastforward        = parse_button_data(dump, 294)?;
+        parsed.stop               = parse_button_data(dump, 300)?;
+        parsed.play               = parse_button_data(dump, 306)?;
+        parsed.record             = parse_button_data(dump, 312)?;
 
         for i in 0..5 {
             let data_dump_index: usize = index_to_data_dump_index(318 + i);
-            parsed.custom_daw_assign[i] = dump[data_dump_index];
+            parsed.custom_daw_assign[i] = U7::new(dump[data_dump_index])?;
         }
 
         Ok(parsed)
@@ -98,7 +219,7 @@ impl Parameters {
     pub fn create_scene_dump(&self) -> [u8; 389] {
         let mut scene_dump: [u8; 389] = [0; 389];
 
-        scene_dump[index_to_data_dump_index(0)] = self.global_channel as u8;
+        scene_dump[index_to_data_dump_index(0)] = self.global_channel.get();
         scene_dump[index_to_data_dump_index(1)] = self.control_mode as u8;
         scene_dump[index_to_data_dump_index(2)] = self.led_mode as u8;
 
@@ -107,6 +228,8 @@ impl Parameters {
             add_group_data_to_dump(&mut scene_dump, &self.groups[i], index);
         }
 
+        scene_dump[index_to_data_dump_index(251)] = self.transport_button_channel.into();
+
         add_button_data_to_dump(&mut scene_dump, &self.track_rewind,       252);
         add_button_data_to_dump(&mut scene_dump, &self.track_fastforward,  258);
         add_button_data_to_dump(&mut scene_dump, &self.cycle,              264);
@@ -121,7 +244,7 @@ impl Parameters {
 
         for i in 0..5 {
             let data_dump_index: usize = index_to_data_dump_index(318 + i);
-            scene_dump[data_dump_index] = self.custom_daw_assign[i];
+            scene_dump[data_dump_index] = self.custom_daw_assign[i].get();
         }
 
         scene_dump
@@ -143,62 +266,233 @@ impl Parameters {
             TransportButton::Record            => &self.record,
         }
     }
+
+    pub fn get_transport_button_parameters_mut(&mut self, button_type: TransportButton)
+    -> &mut ButtonParameters {
+        match button_type {
+            TransportButton::TrackRewind       => &mut self.track_rewind,
+            TransportButton::TrackFastforward  => &mut self.track_fastforward,
+            TransportButton::Cycle             => &mut self.cycle,
+            TransportButton::Set               => &mut self.set,
+            TransportButton::MarkerRewind      => &mut self.marker_rewind,
+            TransportButton::MarkerFastforward => &mut self.marker_fastforward,
+            TransportButton::Rewind            => &mut self.rewind,
+            TransportButton::Fastforward       => &mut self.fastforward,
+            TransportButton::Stop              => &mut self.stop,
+            TransportButton::Play              => &mut self.play,
+            TransportButton::Record            => &mut self.record,
+        }
+    }
+
+    pub fn get_button_parameters(&self, control: ControlId) -> Option<&ButtonParameters> {
+        match control {
+            ControlId::Slider(_) | ControlId::Knob(_) => None,
+            ControlId::Solo(i) => Some(&self.groups[i as usize].solo_button),
+            ControlId::Mute(i) => Some(&self.groups[i as usize].mute_button),
+            ControlId::Record(i) => Some(&self.groups[i as usize].record_button),
+            ControlId::Transport(button_type) => Some(self.get_transport_button_parameters(button_type)),
+        }
+    }
+
+    pub fn get_slider_parameters(&self, control: ControlId) -> Option<&SliderParameters> {
+        match control {
+            ControlId::Slider(i) => Some(&self.groups[i as usize].slider),
+            ControlId::Knob(i) => Some(&self.groups[i as usize].knob),
+            _ => None,
+        }
+    }
+
+    /// The value a button sends when pressed (`on`) or released, `None` for sliders and knobs.
+    pub fn get_button_value(&self, control: ControlId, on: bool) -> Option<U7> {
+        self.get_button_parameters(control).map(|button_parameters| match on {
+            true => button_parameters.on_value,
+            false => button_parameters.off_value,
+        })
+    }
+
+    pub fn get_slider_parameters_mut(&mut self, control: ControlId)
+    -> Option<&mut SliderParameters> {
+        match control {
+            ControlId::Slider(i) => Some(&mut self.groups[i as usize].slider),
+            ControlId::Knob(i) => Some(&mut self.groups[i as usize].knob),
+            _ => None,
+        }
+    }
+
+    pub fn get_button_parameters_mut(&mut self, control: ControlId)
+    -> Option<&mut ButtonParameters> {
+        match control {
+            ControlId::Slider(_) | ControlId::Knob(_) => None,
+            ControlId::Solo(i) => Some(&mut self.groups[i as usize].solo_button),
+            ControlId::Mute(i) => Some(&mut self.groups[i as usize].mute_button),
+            ControlId::Record(i) => Some(&mut self.groups[i as usize].record_button),
+            ControlId::Transport(button_type) =>
+                Some(self.get_transport_button_parameters_mut(button_type)),
+        }
+    }
+
+    pub fn get_control_parameters(&self, control: ControlId) -> ControlParameters<'_> {
+        match control {
+            ControlId::Slider(i) => ControlParameters::Slider(&self.groups[i as usize].slider),
+            ControlId::Knob(i) => ControlParameters::Slider(&self.groups[i as usize].knob),
+            ControlId::Solo(i) => ControlParameters::Button(&self.groups[i as usize].solo_button),
+            ControlId::Mute(i) => ControlParameters::Button(&self.groups[i as usize].mute_button),
+            ControlId::Record(i) =>
+                ControlParameters::Button(&self.groups[i as usize].record_button),
+            ControlId::Transport(button_type) =>
+                ControlParameters::Button(self.get_transport_button_parameters(button_type)),
+        }
+    }
+
+    /// The MIDI channel the given control sends and receives on.
+    pub fn get_control_channel(&self, control: ControlId) -> Channel {
+        match control.get_group_index() {
+            Some(group_index) => self.resolve_channel(self.groups[group_index].channel),
+            None => self.resolve_channel(self.transport_button_channel),
+        }
+    }
+
+    /// Builds a constant-time table from incoming messages to controls, resolving
+    /// `MidiChannel::Global` against `global_channel`.
+    pub fn build_lookup(&self) -> ControlLookup {
+        ControlLookup::new(self)
+    }
+
+    /// Resolves `MidiChannel::Global` against `global_channel`.
+    pub fn resolve_channel(&self, channel: MidiChannel) -> Channel {
+        match channel {
+            MidiChannel::Custom(channel) => channel,
+            MidiChannel::Global => self.global_channel.into(),
+        }
+    }
+}
+
+pub(crate) fn assign_slider_cc(slider: &mut SliderParameters, control_change: CcNumber) {
+    slider.assign_type = SliderAssignType::Enable;
+    slider.note_number = control_change;
+}
+
+pub(crate) fn assign_button_cc(button: &mut ButtonParameters, control_change: CcNumber) {
+    button.assign_type = ButtonAssignType::ControlChange;
+    button.note_number = control_change.into();
 }
 
 const fn index_to_data_dump_index(index: usize) -> usize {
     ((index / 7) * 8) + ((index % 7) + 1)
 }
 
-fn parse_group_data(raw_scene_data: &[u8], index: usize) -> ControllerGroupParameters {
-    ControllerGroupParameters {
+fn parse_group_data(raw_scene_data: &[u8], index: usize) -> Result<ControllerGroupParameters> {
+    Ok(ControllerGroupParameters {
         channel: MidiChannel::from(raw_scene_data[index_to_data_dump_index(index)]),
-        slider: parse_slider_data(&raw_scene_data, index + 1),
-        knob: parse_slider_data(&raw_scene_data, index + 7),
-        solo_button: parse_button_data(&raw_scene_data, index + 13),
-        mute_button: parse_button_data(&raw_scene_data, index + 19),
-        record_button: parse_button_data(&raw_scene_data, index + 25),
-    }
+        slider: parse_slider_data(raw_scene_data, index + 1)?,
+        knob: parse_slider_data(raw_scene_data, index + 7)?,
+        solo_button: parse_button_data(raw_scene_data, index + 13)?,
+        mute_button: parse_button_data(raw_scene_data, index + 19)?,
+        record_button: parse_button_data(raw_scene_data, index + 25)?,
+    })
 }
 
-fn parse_slider_data(raw_scene_data: &[u8], index: usize) -> SliderParameters {
-    SliderParameters {
+fn parse_slider_data(raw_scene_data: &[u8], index: usize) -> Result<SliderParameters> {
+    Ok(SliderParameters {
         assign_type: SliderAssignType::from(raw_scene_data[index_to_data_dump_index(index)]),
-        note_number: raw_scene_data[index_to_data_dump_index(index + 2)],
-        min_value: raw_scene_data[index_to_data_dump_index(index + 3)],
-        max_value: raw_scene_data[index_to_data_dump_index(index + 4)],
-    }
+        note_number: CcNumber::new(raw_scene_data[index_to_data_dump_index(index + 2)])?,
+        min_value: U7::new(raw_scene_data[index_to_data_dump_index(index + 3)])?,
+        max_value: U7::new(raw_scene_data[index_to_data_dump_index(index + 4)])?,
+    })
 }
 
-fn parse_button_data(raw_scene_data: &[u8], index: usize) -> ButtonParameters {
-    ButtonParameters {
+fn parse_button_data(raw_scene_data: &[u8], index: usize) -> Result<ButtonParameters> {
+    Ok(ButtonParameters {
         assign_type: ButtonAssignType::from(raw_scene_data[index_to_data_dump_index(index)]),
         behavior: ButtonBehavior::from(raw_scene_data[index_to_data_dump_index(index + 1)]),
-        note_number: raw_scene_data[index_to_data_dump_index(index + 2)],
-        off_value: raw_scene_data[index_to_data_dump_index(index + 3)],
-        on_value: raw_scene_data[index_to_data_dump_index(index + 4)],
-    }
+        note_number: U7::new(raw_scene_data[index_to_data_dump_index(index + 2)])?,
+        off_value: U7::new(raw_scene_data[index_to_data_dump_index(index + 3)])?,
+        on_value: U7::new(raw_scene_data[index_to_data_dump_index(index + 4)])?,
+    })
 }
 
-fn add_group_data_to_dump(mut dump: &mut [u8], group_params: &ControllerGroupParameters, index: usize) {
+fn add_group_data_to_dump(dump: &mut [u8], group_params: &ControllerGroupParameters, index: usize) {
     dump[index_to_data_dump_index(index)] = group_params.channel.into();
-    add_slider_data_to_dump(&mut dump, &group_params.slider, index_to_data_dump_index(index + 1));
-    add_slider_data_to_dump(&mut dump, &group_params.knob, index_to_data_dump_index(index + 7));
-    add_button_data_to_dump(&mut dump, &group_params.solo_button, index_to_data_dump_index(index + 13));
-    add_button_data_to_dump(&mut dump, &group_params.mute_button, index_to_data_dump_index(index + 19));
-    add_button_data_to_dump(&mut dump, &group_params.record_button, index_to_data_dump_index(index + 25));
+    add_slider_data_to_dump(dump, &group_params.slider, index + 1);
+    add_slider_data_to_dump(dump, &group_params.knob, index + 7);
+    add_button_data_to_dump(dump, &group_params.solo_button, index + 13);
+    add_button_data_to_dump(dump, &group_params.mute_button, index + 19);
+    add_button_data_to_dump(dump, &group_params.record_button, index + 25);
 }
 
 fn add_slider_data_to_dump(dump: &mut [u8], slider_params: &SliderParameters, index: usize) {
     dump[index_to_data_dump_index(index)] = slider_params.assign_type as u8;
-    dump[index_to_data_dump_index(index + 2)] = slider_params.note_number as u8;
-    dump[index_to_data_dump_index(index + 3)] = slider_params.min_value as u8;
-    dump[index_to_data_dump_index(index + 4)] = slider_params.max_value as u8;
+    dump[index_to_data_dump_index(index + 2)] = slider_params.note_number.get();
+    dump[index_to_data_dump_index(index + 3)] = slider_params.min_value.get();
+    dump[index_to_data_dump_index(index + 4)] = slider_params.max_value.get();
 }
 
 fn add_button_data_to_dump(dump: &mut [u8], button_params: &ButtonParameters, index: usize) {
     dump[index_to_data_dump_index(index)] = button_params.assign_type as u8;
     dump[index_to_data_dump_index(index + 1)] = button_params.behavior as u8;
-    dump[index_to_data_dump_index(index + 2)] = button_params.note_number as u8;
-    dump[index_to_data_dump_index(index + 3)] = button_params.off_value as u8;
-    dump[index_to_data_dump_index(index + 4)] = button_params.on_value as u8;
+    dump[index_to_data_dump_index(index + 2)] = button_params.note_number.get();
+    dump[index_to_data_dump_index(index + 3)] = button_params.off_value.get();
+    dump[index_to_data_dump_index(index + 4)] = button_params.on_value.get();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scene with a different value in every field, so that misplaced fields show up.
+    fn distinct_scene() -> Parameters {
+        let mut parameters = Parameters::factory_default();
+        parameters.global_channel = GlobalChannel::from_const(5);
+        parameters.control_mode = ControlMode::Live;
+        parameters.led_mode = LedMode::External;
+        parameters.transport_button_channel = MidiChannel::Custom(Channel::from_const(9));
+
+        let mut n = 0;
+        let mut next = || {
+            n += 1;
+            U7::from_masked(n)
+        };
+        for (i, group) in parameters.groups.iter_mut().enumerate() {
+            group.channel = MidiChannel::Custom(Channel::from_masked(i as u8));
+            for slider in [&mut group.slider, &mut group.knob] {
+                slider.note_number = next().into();
+                slider.min_value = next();
+                slider.max_value = next();
+            }
+            for button in [&mut group.solo_button, &mut group.mute_button,
+                &mut group.record_button] {
+                button.behavior = ButtonBehavior::Toggle;
+                button.note_number = next();
+                button.off_value = next();
+                button.on_value = next();
+            }
+        }
+        for &button_type in TransportButton::ALL.iter() {
+            let button = parameters.get_transport_button_parameters_mut(button_type);
+            button.assign_type = ButtonAssignType::Note;
+            button.note_number = next();
+            button.off_value = next();
+            button.on_value = next();
+        }
+        for value in parameters.custom_daw_assign.iter_mut() {
+            *value = next();
+        }
+        parameters
+    }
+
+    #[test]
+    fn scene_dump_round_trip() {
+        for parameters in [Parameters::factory_default(), distinct_scene()] {
+            let dump = parameters.create_scene_dump();
+            assert_eq!(Parameters::parse_scene_dump(&dump).unwrap(), parameters);
+        }
+    }
+
+    #[test]
+    fn parse_short_scene_dump() {
+        assert!(matches!(Parameters::parse_scene_dump(&[0x00, 0x01]),
+            Err(Error::InvalidSceneDump(2))));
+        assert!(matches!(Parameters::parse_scene_dump(&[0; 388]),
+            Err(Error::InvalidSceneDump(388))));
+    }
 }
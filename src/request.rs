@@ -0,0 +1,143 @@
+use super::connection::{Connection, IoType};
+use super::device::{DeviceMode, DeviceResponse};
+use super::error::Error;
+use super::Result;
+
+/// A request answered by a `DeviceResponse`, described once for the blocking and the async device.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Request<'a> {
+    Mode,
+    NativeMode(IoType),
+    /// Sends a scene dump to the device's current scene.
+    DataLoad(&'a [u8; 389]),
+    /// Writes the current scene to the device's memory. The device answers on the global channel
+    /// of the written scene from then on.
+    Write,
+}
+
+impl Request<'_> {
+    pub(crate) fn send(&self, connection: &mut Connection) -> Result<()> {
+        match *self {
+            Request::Mode => connection.mode_request(),
+            Request::NativeMode(io_type) => connection.native_mode_io_request(io_type),
+            Request::DataLoad(scene_dump) => connection.current_scene_data_dump(scene_dump),
+            Request::Write => connection.scene_write_request(),
+        }
+    }
+
+    /// Whether a response answers the request, either confirming it or reporting an error.
+    pub(crate) fn get_acceptor(&self) -> fn(&DeviceResponse) -> bool {
+        match self {
+            Request::Mode => |response| matches!(response, DeviceResponse::ModeData(_)),
+            Request::NativeMode(_) =>
+                |response| matches!(response, DeviceResponse::NativeModeInOut(_)),
+            Request::DataLoad(_) => |response| matches!(response,
+                DeviceResponse::DataLoadCompleted | DeviceResponse::DataLoadError),
+            Request::Write => |response| matches!(response,
+                DeviceResponse::WriteCompleted | DeviceResponse::WriteError),
+        }
+    }
+
+    /// Turns the accepted response into an error if the device reported one.
+    pub(crate) fn check(&self, response: DeviceResponse) -> Result<DeviceResponse> {
+        match (self, response) {
+            (_, DeviceResponse::DataLoadError) => Err(Error::DataLoadFailed),
+            (_, DeviceResponse::WriteError) => Err(Error::WriteFailed),
+            (Request::NativeMode(io_type), DeviceResponse::NativeModeInOut(mode)) => {
+                let expected_mode = match io_type {
+                    IoType::In => DeviceMode::Native,
+                    IoType::Out => DeviceMode::Normal,
+                };
+                match mode == expected_mode {
+                    true => Ok(DeviceResponse::NativeModeInOut(mode)),
+                    false => Err(Error::WrongDeviceMode(mode)),
+                }
+            },
+            (_, response) => Ok(response),
+        }
+    }
+}
+
+/// Fails with `Error::WrongDeviceMode` when the device is known to be in another mode.
+pub(crate) fn check_mode(current_mode: Option<DeviceMode>, mode: DeviceMode) -> Result<()> {
+    match current_mode {
+        Some(current_mode) if current_mode != mode => Err(Error::WrongDeviceMode(current_mode)),
+        _ => Ok(()),
+    }
+}
+
+type Acceptor<T> = Box<dyn Fn(&T) -> bool + Send>;
+
+struct PendingRequest<T, S> {
+    id: u64,
+    accepts: Acceptor<T>,
+    sender: S,
+}
+
+/// Requests waiting for a reply of type `T`, each with the sender `S` that passes the reply on.
+pub(crate) struct PendingRequests<T, S> {
+    requests: Vec<PendingRequest<T, S>>,
+    next_id: u64,
+}
+
+impl<T, S> Default for PendingRequests<T, S> {
+    fn default() -> Self {
+        PendingRequests {
+            requests: Vec::new(),
+            next_id: 0,
+        }
+    }
+}
+
+impl<T, S> PendingRequests<T, S> {
+    /// Registers a request, returning its ID. Requests are registered before they are sent so
+    /// that a fast reply cannot be missed.
+    pub(crate) fn register<F>(&mut self, accepts: F, sender: S) -> u64 where
+        F: Fn(&T) -> bool + Send + 'static {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.requests.push(PendingRequest { id, accepts: Box::new(accepts), sender });
+        id
+    }
+
+    /// Removes a request that failed, timed out or was cancelled.
+    pub(crate) fn remove(&mut self, id: u64) {
+        self.requests.retain(|request| request.id != id);
+    }
+
+    /// Removes the oldest request accepting `reply`, returning its sender.
+    pub(crate) fn take_sender(&mut self, reply: &T) -> Option<S> {
+        let position = self.requests.iter().position(|request| (request.accepts)(reply))?;
+        Some(self.requests.remove(position).sender)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_responses() {
+        let native_mode = |mode| DeviceResponse::NativeModeInOut(mode);
+        assert!(matches!(Request::Write.check(DeviceResponse::WriteError),
+            Err(Error::WriteFailed)));
+        assert!(Request::Write.check(DeviceResponse::WriteCompleted).is_ok());
+        assert!(matches!(Request::NativeMode(IoType::In).check(native_mode(DeviceMode::Normal)),
+            Err(Error::WrongDeviceMode(DeviceMode::Normal))));
+        assert!(Request::NativeMode(IoType::Out).check(native_mode(DeviceMode::Normal)).is_ok());
+        assert!(!(Request::Mode.get_acceptor())(&native_mode(DeviceMode::Native)));
+    }
+
+    #[test]
+    fn pending_requests_answer_the_oldest_accepting_request() {
+        let mut pending = PendingRequests::default();
+        let first = pending.register(|&reply: &u8| reply > 1, "first");
+        pending.register(|&reply: &u8| reply > 0, "second");
+        pending.register(|&reply: &u8| reply > 0, "third");
+
+        assert_eq!(pending.take_sender(&1), Some("second"));
+        pending.remove(first);
+        assert_eq!(pending.take_sender(&2), Some("third"));
+        assert_eq!(pending.take_sender(&2), None);
+    }
+}
@@ -0,0 +1,140 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::thread::sleep;
+use std::time::Duration;
+
+use midir::MidiOutputConnection;
+
+use super::error::Error;
+use super::event::Event;
+use super::parameters::Parameters;
+use super::Result;
+
+const HEADER: &str = "# korgnanokontrol2 session v1";
+
+/// A single message received from the device, along with the events it was decoded into.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionEntry {
+    /// Microseconds reported by midir.
+    pub timestamp: u64,
+    pub message: Vec<u8>,
+    /// The decoded events in their `Debug` representation.
+    pub events: Vec<String>,
+}
+
+/// A capture of everything received from the device, stored with the scene used to decode it.
+///
+/// Sessions are saved as text, one message per line: the timestamp, the raw bytes in hex and the
+/// decoded events, separated by tabs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Session {
+    pub scene: Option<Vec<u8>>,
+    pub entries: Vec<SessionEntry>,
+}
+
+impl Session {
+    pub fn new(scene: Option<Vec<u8>>) -> Self {
+        Session {
+            scene,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, timestamp: u64, message: &[u8], events: &[Event]) {
+        self.entries.push(SessionEntry {
+            timestamp,
+            message: message.to_vec(),
+            events: events.iter().map(|event| format!("{:?}", event)).collect(),
+        });
+    }
+
+    /// Parses the scene the session was recorded with, if it was stored.
+    pub fn get_parameters(&self) -> Option<Result<Parameters>> {
+        self.scene.as_ref().map(|scene| Parameters::parse_scene_dump(scene))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        if let Some(scene) = &self.scene {
+            writeln!(writer, "scene\t{}", to_hex(scene))?;
+        }
+        for entry in &self.entries {
+            writeln!(writer, "{}\t{}\t{}", entry.timestamp, to_hex(&entry.message),
+                entry.events.join("\t"))?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: BufRead>(reader: R) -> Result<Self> {
+        let mut session = Session::default();
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line_number = i + 1;
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.split('\t');
+            let first = fields.next().unwrap_or_default();
+            let bytes = fields.next()
+                .and_then(from_hex)
+                .ok_or(Error::InvalidSessionFile(line_number))?;
+
+            match first {
+                "scene" => session.scene = Some(bytes),
+                timestamp => session.entries.push(SessionEntry {
+                    timestamp: timestamp.parse().map_err(|_| Error::InvalidSessionFile(line_number))?,
+                    message: bytes,
+                    events: fields.filter(|field| !field.is_empty()).map(str::to_string).collect(),
+                }),
+            }
+        }
+
+        Ok(session)
+    }
+
+    /// Calls `callback` with every entry, sleeping between entries to reproduce the original timing.
+    pub fn play<F>(&self, mut callback: F) -> Result<()> where
+        F: FnMut(&SessionEntry) -> Result<()> {
+
+        let mut previous_timestamp: Option<u64> = None;
+        for entry in &self.entries {
+            if let Some(previous_timestamp) = previous_timestamp {
+                let delay = entry.timestamp.saturating_sub(previous_timestamp);
+                sleep(Duration::from_micros(delay));
+            }
+            previous_timestamp = Some(entry.timestamp);
+            callback(entry)?;
+        }
+        Ok(())
+    }
+
+    /// Sends the recorded messages out of a MIDI port with the original timing.
+    pub fn play_to_port(&self, port: &mut MidiOutputConnection) -> Result<()> {
+        self.play(|entry| {
+            port.send(&entry.message)?;
+            Ok(())
+        })
+    }
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>().join(" ")
+}
+
+pub(crate) fn from_hex(text: &str) -> Option<Vec<u8>> {
+    text.split_whitespace().map(|byte| u8::from_str_radix(byte, 16).ok()).collect()
+}
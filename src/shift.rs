@@ -0,0 +1,188 @@
+use super::data::Data;
+use super::control::ControlId;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Layer {
+    #[default]
+    Base,
+    Shift,
+}
+
+/// Turns a button into a shift key that switches every other control into an alternate layer.
+///
+/// The shift layer keeps its own `Data`, so button LEDs and values set while shifted do not
+/// disturb the base layer.
+#[derive(Debug)]
+pub struct ShiftLayer {
+    button: Option<ControlId>,
+    /// Double tapping the shift button keeps the shift layer active until it is pressed again.
+    pub latch_on_double_tap: bool,
+    /// Maximum number of microseconds between two presses for them to latch the shift layer.
+    pub double_tap_window: u64,
+    held: bool,
+    latched: bool,
+    last_press: Option<u64>,
+    data: Data,
+}
+
+impl Default for ShiftLayer {
+    fn default() -> Self {
+        ShiftLayer {
+            button: None,
+            latch_on_double_tap: false,
+            double_tap_window: 300_000,
+            held: false,
+            latched: false,
+            last_press: None,
+            data: Data::default(),
+        }
+    }
+}
+
+impl ShiftLayer {
+    pub fn new(button: ControlId) -> Self {
+        ShiftLayer {
+            button: Some(button),
+            ..Self::default()
+        }
+    }
+
+    pub fn get_button(&self) -> Option<ControlId> {
+        self.button
+    }
+
+    pub fn set_button(&mut self, button: Option<ControlId>) {
+        self.button = button;
+        self.held = false;
+        self.latched = false;
+        self.last_press = None;
+    }
+
+    pub fn get_layer(&self) -> Layer {
+        match self.held || self.latched {
+            true => Layer::Shift,
+            false => Layer::Base,
+        }
+    }
+
+    pub fn is_latched(&self) -> bool {
+        self.latched
+    }
+
+    /// The values of the controls while shifted.
+    pub fn get_data(&self) -> &Data {
+        &self.data
+    }
+
+    pub fn get_data_mut(&mut self) -> &mut Data {
+        &mut self.data
+    }
+
+    /// Feeds a transition of the shift button, returning the new layer if it changed.
+    pub fn process(&mut self, timestamp: u64, pressed: bool) -> Option<Layer> {
+        let layer = self.get_layer();
+
+        match pressed {
+            true if self.held => (),
+            true => {
+                self.held = true;
+                let double_tap = self.last_press
+                    .is_some_and(|last_press| {
+                        timestamp.saturating_sub(last_press) <= self.double_tap_window
+                    });
+                match self.latched {
+                    true => {
+                        self.latched = false;
+                        self.last_press = None;
+                    },
+                    false => {
+                        self.latched = self.latch_on_double_tap && double_tap;
+                        self.last_press = Some(timestamp);
+                    },
+                }
+            },
+            false => self.held = false,
+        }
+
+        match self.get_layer() {
+            new_layer if new_layer != layer => Some(new_layer),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::TransportButton;
+    use crate::event::{Event, LayerEvent};
+    use crate::types::U7;
+    use crate::KorgNanokontrol2;
+
+    const KNOB_1: u8 = 0x10;
+    const CYCLE: u8 = 0x2E;
+
+    fn device() -> KorgNanokontrol2 {
+        let mut device = KorgNanokontrol2::new();
+        device.set_shift_button(Some(ControlId::Transport(TransportButton::Cycle))).unwrap();
+        device.get_shift_mut().latch_on_double_tap = true;
+        device
+    }
+
+    fn layer_changed(timestamp: u64, layer: Layer, latched: bool) -> Event {
+        Event::LayerChanged(LayerEvent { timestamp, layer, latched })
+    }
+
+    #[test]
+    fn shift_button_is_swallowed() {
+        let mut device = device();
+        device.receive(0, &[0xB0, CYCLE, 0x7F]);
+        device.receive(10, &[0xB0, CYCLE, 0x00]);
+        assert_eq!(device.poll_events(), vec![
+            layer_changed(0, Layer::Shift, false),
+            layer_changed(10, Layer::Base, false),
+        ]);
+        let cycle = ControlId::Transport(TransportButton::Cycle);
+        assert_eq!(device.get_data().get_value(cycle), U7::MIN);
+        assert_eq!(device.get_shift().get_data().get_value(cycle), U7::MIN);
+    }
+
+    #[test]
+    fn latch_on_double_tap() {
+        let mut device = device();
+        device.receive(0, &[0xB0, CYCLE, 0x7F]);
+        device.receive(100_000, &[0xB0, CYCLE, 0x00]);
+        device.receive(200_000, &[0xB0, CYCLE, 0x7F]);
+        device.receive(300_000, &[0xB0, CYCLE, 0x00]);
+        assert_eq!(device.poll_events(), vec![
+            layer_changed(0, Layer::Shift, false),
+            layer_changed(100_000, Layer::Base, false),
+            layer_changed(200_000, Layer::Shift, true),
+        ]);
+        assert!(device.get_shift().is_latched());
+
+        // pressing again unlatches, keeping the layer shifted until the release
+        device.receive(1_000_000, &[0xB0, CYCLE, 0x7F]);
+        device.receive(1_100_000, &[0xB0, CYCLE, 0x00]);
+        assert_eq!(device.poll_events(), vec![layer_changed(1_100_000, Layer::Base, false)]);
+    }
+
+    #[test]
+    fn shifted_values_go_to_the_shift_layer() {
+        let mut device = device();
+        device.receive(0, &[0xB0, KNOB_1, 20]);
+        device.receive(10, &[0xB0, CYCLE, 0x7F]);
+        device.receive(20, &[0xB0, KNOB_1, 90]);
+        let layers: Vec<_> = device.poll_events().into_iter()
+            .filter_map(|event| match event {
+                Event::Control(event) => Some(event.layer),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(layers, vec![Layer::Base, Layer::Shift]);
+
+        let knob = ControlId::Knob(0);
+        assert_eq!(device.get_data().get_value(knob), U7::from_const(20));
+        assert_eq!(device.get_shift().get_data().get_value(knob), U7::from_const(90));
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use super::control::ControlId;
+use super::parameters::Parameters;
+use super::session::Session;
+use super::types::{CcNumber, Channel, U7};
+use super::Result;
+
+/// How recorded messages are split into tracks.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum TrackLayout {
+    /// Everything in a single track, written as a type 0 file.
+    #[default]
+    Single,
+    /// One track per group, plus one for the transport buttons, written as a type 1 file.
+    PerGroup,
+    /// One track per control, written as a type 1 file.
+    PerControl,
+}
+
+/// Exports the channel messages of a `Session` as a Standard MIDI File.
+///
+/// Messages are assigned to controls using the scene stored in the session, or the factory default
+/// scene if it has none. Messages that do not belong to any control are kept in a track of their
+/// own.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SmfExport {
+    pub layout: TrackLayout,
+    /// Ticks per quarter note.
+    pub ppq: u16,
+    /// Microseconds per quarter note.
+    pub tempo: u32,
+}
+
+impl Default for SmfExport {
+    fn default() -> Self {
+        SmfExport {
+            layout: TrackLayout::Single,
+            ppq: 480,
+            tempo: 500_000,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum TrackKey {
+    All,
+    Group(usize),
+    Transport,
+    Control(ControlId),
+    Other,
+}
+
+impl SmfExport {
+    pub fn save<P: AsRef<Path>>(&self, session: &Session, path: P) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(session, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_to<W: Write>(&self, session: &Session, writer: &mut W) -> Result<()> {
+        let parameters = match session.get_parameters() {
+            Some(parameters) => parameters?,
+            None => Parameters::factory_default(),
+        };
+
+        let lookup = parameters.build_lookup();
+        let start = session.entries.iter().map(|entry| entry.timestamp).min().unwrap_or(0);
+        let mut tracks: BTreeMap<TrackKey, Vec<(u64, &[u8])>> = BTreeMap::new();
+        for entry in &session.entries {
+            let status = match entry.message.first() {
+                Some(&status) if (0x80..0xF0).contains(&status) => status,
+                _ => continue,
+            };
+            let midi_channel = Channel::from_masked(status);
+            let control = match (status & 0xF0, entry.message.get(1)) {
+                (0xB0, Some(&control_change)) =>
+                    lookup.find_control_change(midi_channel, CcNumber::from_masked(control_change)),
+                (0x80, Some(&note_number)) | (0x90, Some(&note_number)) =>
+                    lookup.find_note(midi_channel, U7::from_masked(note_number)),
+                _ => None,
+            };
+            let tick = self.get_ticks(entry.timestamp.saturating_sub(start));
+            tracks.entry(self.get_track_key(control)).or_default().push((tick, &entry.message));
+        }
+        // entries of edited or merged sessions may be out of order
+        for events in tracks.values_mut() {
+            events.sort_by_key(|&(tick, _)| tick);
+        }
+
+        let mut chunks = Vec::new();
+        match self.layout {
+            TrackLayout::Single => {
+                let events = tracks.remove(&TrackKey::All).unwrap_or_default();
+                chunks.push(self.create_track(None, &events, true));
+            },
+            TrackLayout::PerGroup | TrackLayout::PerControl => {
+                chunks.push(self.create_track(None, &[], true));
+                for (key, events) in &tracks {
+                    chunks.push(self.create_track(Some(&get_track_name(*key)), events, false));
+                }
+            },
+        }
+
+        let format: u16 = match self.layout {
+            TrackLayout::Single => 0,
+            _ => 1,
+        };
+        writer.write_all(b"MThd")?;
+        writer.write_all(&6u32.to_be_bytes())?;
+        writer.write_all(&format.to_be_bytes())?;
+        writer.write_all(&(chunks.len() as u16).to_be_bytes())?;
+        writer.write_all(&(self.ppq & 0x7FFF).to_be_bytes())?;
+        for chunk in chunks {
+            writer.write_all(b"MTrk")?;
+            writer.write_all(&(chunk.len() as u32).to_be_bytes())?;
+            writer.write_all(&chunk)?;
+        }
+        Ok(())
+    }
+
+    fn get_ticks(&self, microseconds: u64) -> u64 {
+        match self.tempo {
+            0 => 0,
+            tempo => microseconds * self.ppq as u64 / tempo as u64,
+        }
+    }
+
+    fn get_track_key(&self, control: Option<ControlId>) -> TrackKey {
+        match (self.layout, control) {
+            (TrackLayout::Single, _) => TrackKey::All,
+            (_, None) => TrackKey::Other,
+            (TrackLayout::PerGroup, Some(control)) => match control.get_group_index() {
+                Some(group_index) => TrackKey::Group(group_index),
+                None => TrackKey::Transport,
+            },
+            (TrackLayout::PerControl, Some(control)) => TrackKey::Control(control),
+        }
+    }
+
+    fn create_track(&self, name: Option<&str>, events: &[(u64, &[u8])], with_tempo: bool) -> Vec<u8> {
+        let mut track = Vec::new();
+        if let Some(name) = name {
+            write_variable_length(&mut track, 0);
+            track.extend_from_slice(&[0xFF, 0x03]);
+            write_variable_length(&mut track, name.len() as u64);
+            track.extend_from_slice(name.as_bytes());
+        }
+        if with_tempo {
+            write_variable_length(&mut track, 0);
+            track.extend_from_slice(&[0xFF, 0x51, 0x03]);
+            track.extend_from_slice(&self.tempo.to_be_bytes()[1..]);
+        }
+
+        let mut previous_tick = 0;
+        for &(tick, message) in events {
+            write_variable_length(&mut track, tick - previous_tick);
+            track.extend_from_slice(message);
+            previous_tick = tick;
+        }
+
+        write_variable_length(&mut track, 0);
+        track.extend_from_slice(&[0xFF, 0x2F, 0x00]);
+        track
+    }
+}
+
+fn get_track_name(key: TrackKey) -> String {
+    match key {
+        TrackKey::All => "nanoKONTROL2".to_string(),
+        TrackKey::Group(i) => format!("Group {}", i + 1),
+        TrackKey::Transport => "Transport".to_string(),
+        TrackKey::Control(control) => control.to_string(),
+        TrackKey::Other => "Other".to_string(),
+    }
+}
+
+fn write_variable_length(buffer: &mut Vec<u8>, value: u64) {
+    // variable length quantities are limited to four bytes
+    let value = value.min(0x0FFF_FFFF);
+    let mut bytes = vec![(value & 0x7F) as u8];
+    let mut rest = value >> 7;
+    while rest > 0 {
+        bytes.push(0x80 | (rest & 0x7F) as u8);
+        rest >>= 7;
+    }
+    buffer.extend(bytes.iter().rev());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_entries_out_of_order() {
+        let mut session = Session::new(None);
+        session.push(2_000_000, &[0xB0, 0x00, 0x40], &[]);
+        session.push(1_000_000, &[0xB0, 0x00, 0x20], &[]);
+
+        let mut file = Vec::new();
+        SmfExport::default().write_to(&session, &mut file).unwrap();
+        let track = &file[22..];
+        // tempo, then the earlier message after 0 ticks and the later one after 960 ticks
+        assert_eq!(track[..7], [0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20]);
+        assert_eq!(track[7..11], [0x00, 0xB0, 0x00, 0x20]);
+        assert_eq!(track[11..16], [0x87, 0x40, 0xB0, 0x00, 0x40]);
+    }
+
+    #[test]
+    fn export_without_scene_uses_the_factory_default() {
+        let mut session = Session::new(None);
+        session.push(0, &[0xB0, 0x10, 0x40], &[]);
+
+        let export = SmfExport { layout: TrackLayout::PerControl, ..Default::default() };
+        let mut file = Vec::new();
+        export.write_to(&session, &mut file).unwrap();
+        let contains = |name: &[u8]| file.windows(name.len()).any(|window| window == name);
+        assert!(contains(b"Knob 1"));
+        assert!(!contains(b"Other"));
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+
+use super::control::ControlId;
+use super::event::ControlEvent;
+
+type Callback = Box<dyn FnMut(&ControlEvent) + Send>;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Filter {
+    Control(ControlId),
+    AnyButton,
+}
+
+impl Filter {
+    fn matches(&self, control: ControlId) -> bool {
+        match *self {
+            Filter::Control(subscribed_control) => subscribed_control == control,
+            Filter::AnyButton => control.is_button(),
+        }
+    }
+}
+
+struct Entry {
+    id: u64,
+    filter: Filter,
+    callback: Callback,
+}
+
+#[derive(Default)]
+struct Registry {
+    entries: Vec<Entry>,
+    next_id: u64,
+    /// Subscriptions dropped while their callbacks were out of `entries` being called.
+    removed: Vec<u64>,
+}
+
+/// Callbacks subscribed to control events. Cloning shares the same callbacks.
+#[derive(Clone, Default)]
+pub struct Subscriptions {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl Subscriptions {
+    /// Calls `callback` with every event of `control` until the subscription is dropped.
+    pub fn on<F>(&self, control: ControlId, callback: F) -> Subscription where
+        F: FnMut(&ControlEvent) + Send + 'static {
+        self.add(Filter::Control(control), Box::new(callback))
+    }
+
+    /// Calls `callback` with every button event until the subscription is dropped.
+    pub fn on_any_button<F>(&self, callback: F) -> Subscription where
+        F: FnMut(&ControlEvent) + Send + 'static {
+        self.add(Filter::AnyButton, Box::new(callback))
+    }
+
+    /// Calls the callbacks subscribed to the event's control. Callbacks may subscribe and drop
+    /// subscriptions themselves.
+    pub(crate) fn dispatch(&self, event: &ControlEvent) {
+        let mut entries = std::mem::take(&mut lock(&self.registry).entries);
+        for entry in entries.iter_mut().filter(|entry| entry.filter.matches(event.control)) {
+            (entry.callback)(event);
+        }
+
+        let mut registry = lock(&self.registry);
+        let removed = std::mem::take(&mut registry.removed);
+        entries.retain(|entry| !removed.contains(&entry.id));
+        entries.append(&mut registry.entries);
+        registry.entries = entries;
+    }
+
+    fn add(&self, filter: Filter, callback: Callback) -> Subscription {
+        let mut registry = lock(&self.registry);
+        let id = registry.next_id;
+        registry.next_id += 1;
+        registry.entries.push(Entry { id, filter, callback });
+        Subscription {
+            registry: Arc::downgrade(&self.registry),
+            id,
+        }
+    }
+}
+
+/// Keeps a callback subscribed, unsubscribing it when dropped.
+#[must_use = "the callback is unsubscribed as soon as the subscription is dropped"]
+pub struct Subscription {
+    registry: Weak<Mutex<Registry>>,
+    id: u64,
+}
+
+impl Subscription {
+    /// Keeps the callback subscribed for as long as the subscriptions exist.
+    pub fn detach(mut self) {
+        self.registry = Weak::new();
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let registry = match self.registry.upgrade() {
+            Some(registry) => registry,
+            None => return,
+        };
+        let mut registry = lock(&registry);
+        match registry.entries.iter().position(|entry| entry.id == self.id) {
+            Some(position) => {
+                registry.entries.remove(position);
+            },
+            None => registry.removed.push(self.id),
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,190 @@
+use super::types::U7;
+
+/// How a physical control regains control of a value that was changed in software.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum TakeoverMode {
+    /// The value jumps straight to the physical position.
+    #[default]
+    Jump,
+    /// Physical movement is ignored until the control crosses the software value.
+    Pickup,
+    /// Physical movement is scaled so the value converges with the control at either end stop.
+    Scale,
+}
+
+/// Soft takeover state for a single fader or knob.
+#[derive(Debug, Default)]
+pub struct Takeover {
+    pub mode: TakeoverMode,
+    target: Option<U7>,
+    physical: Option<U7>,
+}
+
+impl Takeover {
+    pub fn new(mode: TakeoverMode) -> Self {
+        Takeover {
+            mode,
+            target: None,
+            physical: None,
+        }
+    }
+
+    /// The value set in software that the physical control has not yet caught up with.
+    pub fn target(&self) -> Option<U7> {
+        self.target
+    }
+
+    /// The last position reported by the physical control.
+    pub fn physical(&self) -> Option<U7> {
+        self.physical
+    }
+
+    pub fn is_synchronized(&self) -> bool {
+        self.target.is_none()
+    }
+
+    /// Records a value changed in software, desynchronizing the physical control from it.
+    pub fn set_target(&mut self, value: U7) {
+        self.target = match self.physical {
+            Some(physical) if physical == value => None,
+            _ => Some(value),
+        };
+    }
+
+    /// Feeds a value received from the device, returning the value to report, if any.
+    pub fn process(&mut self, value: U7) -> Option<U7> {
+        let previous = self.physical.replace(value);
+
+        let target = match self.target {
+            Some(target) => target,
+            None => return Some(value),
+        };
+
+        match self.mode {
+            TakeoverMode::Jump => {
+                self.target = None;
+                Some(value)
+            },
+            TakeoverMode::Pickup => {
+                let crossed = match previous {
+                    // reaching the target counts as crossing it from either side
+                    Some(previous) => (previous <= target && value >= target)
+                        || (previous >= target && value <= target),
+                    None => value == target,
+                };
+                match crossed {
+                    true => {
+                        self.target = None;
+                        Some(value)
+                    },
+                    false => None,
+                }
+            },
+            TakeoverMode::Scale => {
+                let previous = match previous {
+                    Some(previous) if previous != value => previous,
+                    _ => return None,
+                };
+
+                let (target, previous, physical) = (target.get(), previous.get(), value.get());
+                let scaled = match physical > previous {
+                    true if previous < 127 => {
+                        let headroom = (127 - target) as u32 * (physical - previous) as u32;
+                        target + (headroom / (127 - previous) as u32) as u8
+                    },
+                    false if previous > 0 => {
+                        let headroom = target as u32 * (previous - physical) as u32;
+                        target - (headroom / previous as u32) as u8
+                    },
+                    _ => physical,
+                };
+                let scaled = U7::from_masked(scaled);
+
+                self.target = match scaled == value {
+                    true => None,
+                    false => Some(scaled),
+                };
+                Some(scaled)
+            },
+        }
+    }
+}
+
+/// Soft takeover state for the continuous controls of one group, laid out like `GroupData`.
+#[derive(Debug, Default)]
+pub struct GroupTakeover {
+    pub slider: Takeover,
+    pub knob: Takeover,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn takeover(mode: TakeoverMode, physical: u8, target: u8) -> Takeover {
+        let mut takeover = Takeover::new(mode);
+        takeover.process(U7::from_const(physical));
+        takeover.set_target(U7::from_const(target));
+        takeover
+    }
+
+    fn process(takeover: &mut Takeover, physical: u8) -> Option<u8> {
+        takeover.process(U7::from_const(physical)).map(U7::get)
+    }
+
+    #[test]
+    fn jump() {
+        let mut takeover = takeover(TakeoverMode::Jump, 70, 64);
+        assert_eq!(process(&mut takeover, 68), Some(68));
+        assert!(takeover.is_synchronized());
+    }
+
+    #[test]
+    fn pickup_from_above() {
+        let mut takeover = takeover(TakeoverMode::Pickup, 70, 64);
+        assert_eq!(process(&mut takeover, 68), None);
+        assert_eq!(process(&mut takeover, 66), None);
+        assert_eq!(process(&mut takeover, 64), Some(64));
+        assert_eq!(process(&mut takeover, 63), Some(63));
+        assert!(takeover.is_synchronized());
+    }
+
+    #[test]
+    fn pickup_from_below() {
+        let mut takeover = takeover(TakeoverMode::Pickup, 20, 64);
+        assert_eq!(process(&mut takeover, 40), None);
+        assert_eq!(process(&mut takeover, 64), Some(64));
+        assert!(takeover.is_synchronized());
+    }
+
+    #[test]
+    fn pickup_jumping_over_the_target() {
+        let mut takeover = takeover(TakeoverMode::Pickup, 20, 64);
+        assert_eq!(process(&mut takeover, 60), None);
+        assert_eq!(process(&mut takeover, 70), Some(70));
+        assert!(takeover.is_synchronized());
+    }
+
+    #[test]
+    fn scale_to_the_top() {
+        let mut takeover = takeover(TakeoverMode::Scale, 0, 64);
+        let mut value = None;
+        for physical in (10..=127).step_by(10).chain(Some(127)) {
+            value = process(&mut takeover, physical);
+        }
+        assert_eq!(value, Some(127));
+        assert!(takeover.is_synchronized());
+    }
+
+    #[test]
+    fn scale_to_the_bottom() {
+        let mut takeover = takeover(TakeoverMode::Scale, 127, 64);
+        assert_eq!(process(&mut takeover, 117).map(|value| value < 64), Some(true));
+        let mut value = None;
+        for physical in (0..117).rev().step_by(10).chain(Some(0)) {
+            value = process(&mut takeover, physical);
+        }
+        assert_eq!(value, Some(0));
+        assert!(takeover.is_synchronized());
+    }
+}
@@ -0,0 +1,101 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use super::error::Error;
+use super::Result;
+
+macro_rules! bounded_u8 {
+    ($(#[$attr:meta])* $name:ident, $max:expr, $error:path) => {
+        $(#[$attr])*
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+        pub struct $name(u8);
+
+        impl $name {
+            pub const MIN: $name = $name(0);
+            pub const MAX: $name = $name($max);
+
+            pub fn new(n: u8) -> Result<Self> {
+                match n {
+                    n if n <= $max => Ok($name(n)),
+                    n => Err($error(n)),
+                }
+            }
+
+            /// Creates the value from a constant, failing to compile when used in a const context
+            /// with an out of range value, and panicking otherwise.
+            pub const fn from_const(n: u8) -> Self {
+                assert!(n <= $max, "value out of range");
+                $name(n)
+            }
+
+            /// Creates the value from the low bits of `n`, as found in incoming MIDI data.
+            pub const fn from_masked(n: u8) -> Self {
+                $name(n & $max)
+            }
+
+            pub const fn get(self) -> u8 {
+                self.0
+            }
+        }
+
+        impl TryFrom<u8> for $name {
+            type Error = Error;
+
+            fn try_from(n: u8) -> Result<Self> {
+                Self::new(n)
+            }
+        }
+
+        impl From<$name> for u8 {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+    };
+}
+
+bounded_u8!(
+    /// A 7-bit MIDI data value, such as a note number, velocity or controller value.
+    U7, 127, Error::InvalidMidiValue);
+
+bounded_u8!(
+    /// A control change number.
+    CcNumber, 127, Error::InvalidMidiValue);
+
+bounded_u8!(
+    /// A MIDI channel, 0-15.
+    Channel, 15, Error::InvalidMidiChannel);
+
+bounded_u8!(
+    /// The device's global MIDI channel, 0-15.
+    GlobalChannel, 15, Error::InvalidGlobalChannel);
+
+impl From<CcNumber> for U7 {
+    fn from(control_change: CcNumber) -> Self {
+        U7(control_change.0)
+    }
+}
+
+impl From<U7> for CcNumber {
+    fn from(value: U7) -> Self {
+        CcNumber(value.0)
+    }
+}
+
+impl From<GlobalChannel> for Channel {
+    fn from(global_channel: GlobalChannel) -> Self {
+        Channel(global_channel.0)
+    }
+}
+
+impl From<Channel> for GlobalChannel {
+    fn from(channel: Channel) -> Self {
+        GlobalChannel(channel.0)
+    }
+}